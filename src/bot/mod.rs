@@ -0,0 +1,164 @@
+// External bots: any program can play via a line-based protocol on its
+// stdin/stdout, similar to a chess engine. Every command is a single line,
+// answers are expected only for `place` and `fire`:
+//
+//   board <dim>                      dimension of the N x N board
+//   ship <size> <amount> <name>      one line per ship type of the fleet
+//   place                            answer "<coordinate> <h|v>" for every single ship,
+//                                    one per line in the order of the fleet
+//   fire                             answer the "<coordinate>" to fire at
+//   result <hit|miss> <coordinate>   outcome of the own shot
+//   sunk <name>                      the own shot sank the ship
+//   game over <win|loss>             the bot should exit now
+//
+// Coordinates are given like "B4" (column letter, row number; answers may use
+// any format of `Coord`), orientation "h" means rightwards and "v" upwards from
+// the coordinate. A bot that answers too late, exits or breaks the rules
+// forfeits the game.
+use model;
+use model::ai::Strategy;
+use model::types::{Board, PlayerType, ShipType, ShotOutcome};
+use rand::StdRng;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+pub struct Bot {
+    command: String,
+    child: Child,
+    stdin: ChildStdin,
+    // lines written by the bot, read by a separate thread
+    lines: Receiver<String>,
+    timeout: Duration,
+}
+
+impl Bot {
+    /// Launches the given command line. The bot has to answer every request
+    /// within the timeout.
+    pub fn launch(command: &str, timeout: Duration) -> Result<Bot, String> {
+        let mut parts = command.split_whitespace();
+        let program = match parts.next() {
+            Some(program) => program,
+            None => return Err("no bot command given".to_string()),
+        };
+
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("could not start '{}': {}", command, e))?;
+
+        let stdin = child.stdin.take().expect("stdin of the bot is piped");
+        let stdout = child.stdout.take().expect("stdout of the bot is piped");
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if tx.send(line).is_err() { break },
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(Bot {
+            command: command.to_string(),
+            child: child,
+            stdin: stdin,
+            lines: rx,
+            timeout: timeout,
+        })
+    }
+
+    /// Sends a single command to the bot
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| format!("'{}' stopped reading its input", self.command))
+    }
+
+    /// Waits for the next non-empty line of the bot
+    fn receive(&mut self) -> Result<String, String> {
+        loop {
+            match self.lines.recv_timeout(self.timeout) {
+                Ok(ref line) if line.trim().is_empty() => continue,
+                Ok(line) => return Ok(line.trim().to_string()),
+                Err(RecvTimeoutError::Timeout) => return Err(format!(
+                    "'{}' didn't answer within {} ms", self.command, self.timeout.as_millis()
+                )),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(format!("'{}' exited", self.command))
+                },
+            }
+        }
+    }
+}
+
+impl Strategy for Bot {
+    fn player_type(&self) -> PlayerType {
+        PlayerType::Bot
+    }
+
+    fn place(&mut self, dim: usize, fleet: &[ShipType], _: &mut StdRng) -> Result<Board, String> {
+        self.send(&format!("board {}", dim))?;
+        for ship in fleet {
+            self.send(&format!("ship {} {} {}", ship.size, ship.amount, ship.name))?;
+        }
+        self.send("place")?;
+
+        let mut board = Board::init(dim);
+        for ship in fleet {
+            for _ in 0..ship.amount {
+                let answer = self.receive()?;
+                let mut words = answer.split_whitespace();
+                let start = words.next().and_then(|c| board.get_index(c));
+                let ori = words.next().map(|o| o.to_lowercase());
+                let indices = match (start, ori) {
+                    (Some(start), Some(ref ori)) if ori == "h" || ori == "v" => {
+                        model::ship_indices(&board, start, ship.size, ori)
+                    },
+                    _ => return Err(format!("invalid placement '{}'", answer)),
+                };
+                match indices {
+                    Some(indices) => board.place_ship(ship.clone(), indices),
+                    None => return Err(format!(
+                        "the {} can't be placed at '{}'", ship.name, answer
+                    )),
+                }
+            }
+        }
+        Ok(board)
+    }
+
+    fn target(&mut self, board: &Board, _: &mut StdRng) -> Result<usize, String> {
+        self.send("fire")?;
+        let answer = self.receive()?;
+        board.get_index(&answer).ok_or(format!("illegal move '{}'", answer))
+    }
+
+    fn record(&mut self, board: &Board, idx: usize, outcome: &ShotOutcome) {
+        // A bot that stopped reading forfeits with its next answer.
+        let result = if *outcome == ShotOutcome::Miss { "miss" } else { "hit" };
+        let _ = self.send(&format!("result {} {}", result, board.coordinate(idx)));
+        if let ShotOutcome::Sunk(ref name) = *outcome {
+            let _ = self.send(&format!("sunk {}", name));
+        }
+    }
+
+    fn game_over(&mut self, won: bool) {
+        let _ = self.send(if won { "game over win" } else { "game over loss" });
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        // give the bot a moment to exit on its own after "game over"
+        thread::sleep(Duration::from_millis(100));
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
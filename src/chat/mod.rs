@@ -0,0 +1,169 @@
+// Chat: the players of a network game talk to each other by typing lines that
+// start with `/say`, at any time: while placing the ships, at their turn and while
+// the opponent takes theirs. The terminal and the connection are read on threads
+// of their own, which hand everything to the game through one channel. So the game
+// can react to both while waiting for either and stays the only one writing to the
+// connection. Own chat lines are sent as `Chat` with the name of the player, chat
+// messages of the opponent are shown right away, everything else is passed on to
+// the game, see `read_line()` and `receive()`.
+// The host shows the name of the client from its hello, not the one in its messages.
+// In a lobby the referee relays the chat between the players, see lobby.
+use net::{self, NetError};
+use net::types::MessageType;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::net::TcpStream;
+use std::sync::{Mutex, MutexGuard};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use term_painter::ToStyle;
+use term_painter::Color::*;
+
+/// What the game waits for
+enum Event {
+    // a line typed by the player
+    Line(String),
+    // a message of the other side, or why the connection failed
+    Message(Result<MessageType, NetError>),
+}
+
+struct Chat {
+    name: String,
+    // the name of the opponent from its hello, if its messages can't be trusted
+    peer: Option<String>,
+    // only written by the game thread, like the connection of the game
+    stream: TcpStream,
+    events: Receiver<Event>,
+    // messages for the game that arrived while it waited for the player
+    pending: VecDeque<Result<MessageType, NetError>>,
+    // the connection failed, the reason was passed on already
+    closed: bool,
+}
+
+// the chat of the running game, there is at most one per process
+static CHAT: Mutex<Option<Chat>> = Mutex::new(None);
+
+fn lock() -> MutexGuard<'static, Option<Chat>> {
+    // the chat stays usable even if a thread panicked while holding it
+    CHAT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Opens the chat of the player with the given name on the connection of the game.
+/// Chat messages of the opponent are shown with the name of the peer, if given.
+/// From now on every message of the game has to be read with `receive()`.
+pub fn open(stream: &TcpStream, name: &str, peer: Option<&str>) -> Result<(), NetError> {
+    let (tx, rx) = mpsc::channel();
+
+    let mut connection = stream.try_clone()?;
+    let messages = tx.clone();
+    thread::spawn(move || loop {
+        let received = net::receive(&mut connection);
+        let failed = received.is_err();
+        if messages.send(Event::Message(received)).is_err() || failed {
+            break;
+        }
+    });
+
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            match line {
+                Ok(line) => if tx.send(Event::Line(line.trim_end().to_string())).is_err() {
+                    break
+                },
+                Err(_) => break,
+            }
+        }
+    });
+
+    *lock() = Some(Chat {
+        name: name.to_string(),
+        peer: peer.map(|p| p.to_string()),
+        stream: stream.try_clone()?,
+        events: rx,
+        pending: VecDeque::new(),
+        closed: false,
+    });
+    Cyan.with(|| println!("Type '/say <message>' to chat with your opponent at any time."));
+    Ok(())
+}
+
+/// Reads the next line of the player that isn't a chat message.
+/// Returns None without an open chat, the terminal is read directly then.
+pub fn read_line() -> Option<String> {
+    let mut chat = lock();
+    let chat = chat.as_mut()?;
+    loop {
+        match chat.events.recv() {
+            Ok(Event::Line(line)) => match said(&line) {
+                Some(text) => chat.say(text),
+                None => return Some(line),
+            },
+            Ok(Event::Message(Ok(MessageType::Chat(name, text)))) => chat.show(&name, &text),
+            Ok(Event::Message(received)) => chat.pending.push_back(received),
+            // the terminal is closed and the connection is gone
+            Err(_) => return Some(String::new()),
+        }
+    }
+}
+
+/// Waits for the next message of the other side for the game, meanwhile the
+/// player may chat. Without an open chat the message is read from the stream.
+pub fn receive(stream: &mut TcpStream) -> Result<MessageType, NetError> {
+    let mut chat = lock();
+    let chat = match chat.as_mut() {
+        Some(chat) => chat,
+        None => return net::receive(stream),
+    };
+    let received = match chat.pending.pop_front() {
+        Some(received) => received,
+        None if chat.closed => return Err(NetError::Disconnected),
+        None => loop {
+            match chat.events.recv() {
+                Ok(Event::Line(line)) => match said(&line) {
+                    Some(text) => chat.say(text),
+                    None => Red.with(|| println!(
+                        "Please wait, only '/say <message>' works right now."
+                    )),
+                },
+                Ok(Event::Message(Ok(MessageType::Chat(name, text)))) => {
+                    chat.show(&name, &text)
+                },
+                Ok(Event::Message(received)) => break received,
+                Err(_) => break Err(NetError::Disconnected),
+            }
+        },
+    };
+    if received.is_err() {
+        chat.closed = true;
+    }
+    received
+}
+
+impl Chat {
+    /// Sends a chat message of the player
+    fn say(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let msg = MessageType::Chat(self.name.clone(), text.to_string());
+        if let Err(e) = net::send(&mut self.stream, msg) {
+            Red.with(|| println!("Your message could not be sent: {}", e));
+        }
+    }
+
+    /// Shows a chat message of the opponent
+    fn show(&self, name: &str, text: &str) {
+        let name = self.peer.as_deref().unwrap_or(name);
+        println!("{} {}", Yellow.paint(format!("{}:", name)), text);
+    }
+}
+
+/// The message of a chat line, e.g. "/say hello" => "hello"
+fn said(line: &str) -> Option<&str> {
+    let mut words = line.trim().splitn(2, char::is_whitespace);
+    match words.next() {
+        Some("/say") => Some(words.next().unwrap_or("").trim()),
+        _ => None,
+    }
+}
@@ -1,170 +1,505 @@
-use bincode;
-use bincode::serde::deserialize_from;
+use chat;
 use ctrlc;
 use model;
-use model::types::{Player, SubField};
-use net;
-use net::types::{self, MessageType};
+use model::ai::Strategy;
+use model::engine::GameEngine;
+use model::replay::Replay;
+use model::types::{Board, Player, PlayerId, PlayerType, ShotOutcome};
+use net::{self, hello, NetError};
+use net::types::MessageType;
+use rand::StdRng;
 use std::net::{Shutdown, TcpStream};
 use term_painter::ToStyle;
 use term_painter::Color::*;
 use util;
 
-/// Connecting the player to IP:port
-pub fn connect(client: Player, ip: &str, port: u16) {
-    // create client instance and connect to server
-    let connection = TcpStream::connect((ip, port)).unwrap();
+/// What the player chose for the game on the command line
+pub struct Options<'a> {
+    /// file of the board configuration, loaded once the board dimension is known
+    pub board: Option<&'a str>,
+    /// room to join or create if the server is a lobby, see `lobby()`
+    pub room: Option<&'a str>,
+    /// let spectators see both fleets if the opponent agrees, see spectate
+    pub reveal: bool,
+}
+
+/// Connecting the player to host:port, the host may be a name, an IPv4 or an IPv6 address
+/// If a bot is given, it plays instead of the player.
+pub fn connect(
+    client: Player,
+    options: Options,
+    bot: Option<Box<dyn Strategy>>,
+    rng: StdRng,
+    host: &str,
+    port: u16
+) -> Result<(), NetError> {
+    // create client instance and connect to server, trying every address of the host
+    let addresses = net::resolve(host, port)?;
+    let mut connection = TcpStream::connect(&addresses[..]).map_err(|e| {
+        NetError::Io(format!("could not connect to {}", net::display_address(host, port)), e)
+    })?;
 
     // add CTRL+C system hook, so that connection partner is informed about disconnect
-    let client_conn_clone = connection.try_clone().unwrap();
-    ctrlc::set_handler(move || {
-        net::send(&mut client_conn_clone.try_clone().unwrap(), MessageType::Quit);
-        client_conn_clone.shutdown(Shutdown::Both).expect("shutdown call failed");
-    }).expect("Error setting Ctrl+C handler");
+    let client_conn_clone = connection.try_clone()?;
+    let handler = ctrlc::set_handler(move || {
+        if let Ok(mut conn) = client_conn_clone.try_clone() {
+            let _ = net::send(&mut conn, MessageType::Quit);
+        }
+        let _ = client_conn_clone.shutdown(Shutdown::Both);
+    });
+    if let Err(e) = handler {
+        Red.with(|| println!("Could not set the Ctrl+C handler: {:?}", e));
+    }
 
-    play(connection, client);
+    let result = play(&mut connection, client, &options, bot, rng);
+    if let Err(NetError::Protocol(_)) = result {
+        let _ = net::send(&mut connection, MessageType::Quit);
+    }
+    let _ = connection.shutdown(Shutdown::Both);
+    result
 }
 
-/// Actual game flow
-fn play(mut connection: TcpStream, mut client: Player) {
-    let mut host_name = "SERVER".to_string();
+const ME: PlayerId = PlayerId::One;
+const HOST: PlayerId = PlayerId::Two;
+
+/// Exchanges the hellos with the server, see net::hello, and sets up the round
+/// with the rules determined by the server. Returns None if the player left the lobby.
+fn handshake(
+    connection: &mut TcpStream,
+    mut client: Player,
+    options: &Options
+) -> Result<Option<(GameEngine, String)>, NetError> {
+    let mut capabilities = hello::CAPABILITIES.to_vec();
+    if options.reveal {
+        capabilities.push(hello::REVEAL);
+    }
+    let client_hello = hello::Hello::new(&client.name, None, &capabilities);
+    hello::send(connection, &client_hello)?;
+    let host_hello = hello::receive(connection)?;
+    let rules = host_hello.rules
+        .ok_or_else(|| NetError::Handshake("the server didn't send the rules".to_string()))?;
+
+    let capabilities = match net::receive(connection)? {
+        MessageType::Welcome(msg, capabilities) => {
+            Yellow.with(|| println!("{}", msg));
+            capabilities
+        },
+        MessageType::Refused(reason) => {
+            return Err(NetError::Handshake(format!("the server refused the game: {}", reason)))
+        },
+        MessageType::Quit => return Err(NetError::Disconnected),
+        _ => return Err(NetError::Handshake("the server didn't send a welcome".to_string())),
+    };
+
+    // in a lobby the opponent is found first
+    let host_name = if capabilities.iter().any(|c| c == hello::LOBBY) {
+        match lobby(connection, options.room)? {
+            Some(opponent) => opponent,
+            None => return Ok(None),
+        }
+    } else {
+        host_hello.name
+    };
+
+    let (dim, fleet) = (rules.dim, rules.fleet);
+    let loaded = options.board.map(|b| util::read_extern_board(b, dim, &fleet));
+    let own_board = match loaded {
+        Some(Ok(b)) => Some(b),
+        Some(Err(e)) => {
+            Red.with(|| println!("Invalid board configuration: {}", e));
+            Red.with(|| println!("Please place your ships."));
+            None
+        },
+        None => None,
+    };
+    client.own_board = Board::init(dim);
+    client.op_board = Board::init(dim);
+
+    // the board of the host is only known by its answers
+    let host = Player {
+        own_board: Board::init(dim),
+        op_board: Board::init(dim),
+        player_type: PlayerType::Human,
+        name: host_name.clone(),
+        capacity: 0,
+    };
+    let mut engine = GameEngine::new(client, host, fleet);
+    engine.set_hidden(HOST);
+    if let Some(board) = own_board {
+        // checked against the fleet already
+        if let Err(e) = engine.set_board(ME, &board) {
+            Red.with(|| println!("Invalid board configuration: {}", e));
+        }
+    }
+    Ok(Some((engine, host_name)))
+}
+
+/// Finds an opponent in the lobby: joins the given room if someone waits there,
+/// otherwise creates it. Without a room the player chooses.
+/// Returns the name of the opponent, or None if the player left the lobby.
+fn lobby(connection: &mut TcpStream, room: Option<&str>) -> Result<Option<String>, NetError> {
+    if let Some(room) = room {
+        net::send(connection, MessageType::List)?;
+        let request = match lobby_answer(connection)? {
+            MessageType::Rooms(ref rooms, _) if rooms.iter().any(|(r, _)| r == room) => {
+                MessageType::Join(room.to_string())
+            },
+            MessageType::Rooms(..) => MessageType::Create(room.to_string()),
+            _ => return Err(NetError::Protocol("expected the list of rooms".to_string())),
+        };
+        net::send(connection, request)?;
+        return match lobby_answer(connection)? {
+            MessageType::Start(opponent) => Ok(Some(opponent)),
+            MessageType::Refused(reason) => Err(NetError::Handshake(reason)),
+            _ => Err(NetError::Protocol("expected the start of the match".to_string())),
+        };
+    }
+
     loop {
-        let recv: Result<types::MessageType, _> =
-            deserialize_from(&mut connection, bincode::SizeLimit::Infinite);
-        match recv {
-            Ok(received) => {
-                // process_message(received);
-                match received {
-                    MessageType::Welcome(msg, host) => {
-                        Yellow.with(|| println!("{}", (msg)));
-                        host_name = host;
-                        net::send(&mut connection, MessageType::Login(client.name.clone()));
-                    },
-                    MessageType::Ping => {
-                        net::send(&mut connection, MessageType::Ping);
-                    },
-                    MessageType::Quit => {
-                        println!("Server ended the connection.");
-                        break;
-                    },
-                    MessageType::RequestCoord => {
-                        Yellow.with(|| print!("It's your turn! "));
-                        // send coordinate to shoot
-                        let mut coord;
-                        loop {
+        Yellow.with(|| println!(
+            "Please enter a command (list, create <room>, join <room>, quit): "
+        ));
+        let input = util::read_string();
+        let mut words = input.trim().splitn(2, ' ');
+        let request = match (words.next(), words.next().map(|w| w.trim())) {
+            (Some("list"), None) => MessageType::List,
+            (Some("create"), Some(room)) => MessageType::Create(room.to_string()),
+            (Some("join"), Some(room)) => MessageType::Join(room.to_string()),
+            (Some("quit"), None) => {
+                net::send(connection, MessageType::Quit)?;
+                return Ok(None);
+            },
+            _ => {
+                Red.with(|| print!("Unknown command! "));
+                continue;
+            },
+        };
+        net::send(connection, request)?;
+
+        match lobby_answer(connection)? {
+            MessageType::Rooms(rooms, idle) => {
+                if rooms.is_empty() {
+                    println!("No one is waiting in a room.");
+                }
+                for (room, name) in rooms {
+                    println!("  {:<20} {} is waiting", room, name);
+                }
+                if !idle.is_empty() {
+                    println!("In the lobby: {}", idle.join(", "));
+                }
+            },
+            MessageType::Refused(reason) => Red.with(|| println!("{}", reason)),
+            MessageType::Start(opponent) => return Ok(Some(opponent)),
+            _ => return Err(NetError::Protocol("unexpected answer of the lobby".to_string())),
+        }
+    }
+}
+
+/// Waits for the answer of the lobby to a request, meanwhile the messages of
+/// the lobby are shown and its pings answered
+fn lobby_answer(connection: &mut TcpStream) -> Result<MessageType, NetError> {
+    loop {
+        match net::receive(connection)? {
+            MessageType::Ping => net::send(connection, MessageType::Ping)?,
+            MessageType::Text(t) => Cyan.with(|| println!("{}", t)),
+            MessageType::Quit => return Err(NetError::Disconnected),
+            msg => return Ok(msg),
+        }
+    }
+}
+
+/// Actual game flow, the game is recorded even if the connection fails
+fn play(
+    connection: &mut TcpStream,
+    client: Player,
+    options: &Options,
+    mut bot: Option<Box<dyn Strategy>>,
+    mut rng: StdRng
+) -> Result<(), NetError> {
+    let (mut engine, host_name) = match handshake(connection, client, options)? {
+        Some(round) => round,
+        None => return Ok(()),
+    };
+    chat::open(connection, &engine.player(ME).name, None)?;
+    // the outcome as announced by the server and the revealed board of the host
+    let mut winner = None;
+    let mut revealed = None;
+
+    let result = take_turns(
+        connection, &mut engine, &host_name, &mut bot, &mut rng, &mut winner, &mut revealed
+    );
+
+    if !engine.shots().is_empty() {
+        let mut replay = Replay::new(&engine);
+        replay.winner = winner.or(replay.winner);
+        if let Some(board) = revealed {
+            replay.reveal(HOST, board);
+        }
+        replay.record();
+    }
+    result
+}
+
+/// Follows the requests of the server until it ends the connection
+fn take_turns(
+    connection: &mut TcpStream,
+    engine: &mut GameEngine,
+    host_name: &str,
+    bot: &mut Option<Box<dyn Strategy>>,
+    rng: &mut StdRng,
+    winner: &mut Option<PlayerId>,
+    revealed: &mut Option<Board>
+) -> Result<(), NetError> {
+    let fleet = engine.fleet().to_vec();
+
+    // Hidden boards: the commitment of the host, the own placement with its
+    // salt and the answers of the host to our shots, see net::commit
+    let mut host_commitment = None;
+    let mut placement = Board::init(0);
+    let salt = net::commit::salt();
+    let mut answers = Vec::new();
+    loop {
+        let received = match chat::receive(connection) {
+            Ok(received) => received,
+            // the server may close the connection once the game is decided
+            Err(NetError::Disconnected) if winner.is_some() => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        match received {
+            MessageType::Ping => {
+                net::send(connection, MessageType::Ping)?;
+            },
+            MessageType::Quit if winner.is_some() => {
+                println!("Server ended the connection.");
+                return Ok(());
+            },
+            MessageType::Quit => return Err(NetError::Disconnected),
+            MessageType::RequestCoord => {
+                engine.set_turn(ME);
+                Yellow.with(|| print!("It's your turn! "));
+                // the field to shoot at
+                let idx = {
+                    let op_board = &engine.player(ME).op_board;
+                    match *bot {
+                        Some(ref mut bot) => match bot.target(op_board, rng) {
+                            Ok(id) => id,
+                            Err(e) => {
+                                Red.with(|| println!("The bot forfeits the game: {}", e));
+                                return net::send(connection, MessageType::Quit);
+                            },
+                        },
+                        None => loop {
                             Yellow.with(|| println!("Please enter a valid coordinate: "));
-                            coord = util::read_string();
-                            if ::model::valid_coordinate(&coord) {
-                                break;
+                            if let Some(id) = op_board.get_index(&util::read_string()) {
+                                break id;
                             }
                             Red.with(|| print!("Invalid coordinate! "));
-                        }
-
-                        net::send(&mut connection, MessageType::Shoot(coord));
-
-                        // receive updated opponent board
-                        let result: Result<types::MessageType, _> =
-                            deserialize_from(&mut connection, bincode::SizeLimit::Infinite);
-                        match result {
-                            Ok(res) => {
-                                match res {
-                                    MessageType::Hit(id) => {
-                                        let row = id / 10;
-                                        let col = id % 10;
-                                        Green.with(|| println!("Hit!"));
-                                        client.op_board[row][col] = SubField::Hit;
-                                    }
-                                    MessageType::Miss(id) => {
-                                        let row = id / 10;
-                                        let col = id % 10;
-                                        Blue.with(|| println!("Miss!"));
-                                        client.op_board[row][col] = SubField::Miss;
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            Err(_) => println!("Did not receive Hit or Miss message.")
-                        }
-                        model::print_boards(&client);
+                        },
                     }
-                    MessageType::RequestBoard => {
-                        model::print_boards(&client);
-
-                        if client.capacity == 0 {
-                            loop {
-                                match model::place_ships(&mut client) {
-                                    Ok(()) => { break; },
-                                    Err(model::types::ErrorType::DeadEndHuman) => {
-                                        Red.with(|| println!(
-                                            "No suitable position left, {}",
-                                            "please restart the ship placement.")
-                                        );
-                                        model::restart_placement(&mut client);
-                                    },
-                                    Err(_) => {
-                                        Red.with(|| println!("Failed placing ships!"));
-                                    },
-                                }
-                            }
-                        }
+                };
+
+                let coord = engine.player(ME).op_board.coordinate(idx);
+                net::send(connection, MessageType::Shoot(coord))?;
 
-                        // send board
-                        net::send(
-                            &mut connection,
-                            MessageType::Board(client.own_board.clone())
-                        );
+                // receive updated opponent board, the answer has to be about the same field
+                let outcome = match chat::receive(connection)? {
+                    MessageType::Hit(id) if id == idx => {
+                        Green.with(|| println!("Hit!"));
+                        ShotOutcome::Hit
                     }
-                    MessageType::Text(t) => {
-                        Cyan.with(|| println!("{}", t));
+                    MessageType::Miss(id) if id == idx => {
+                        Blue.with(|| println!("Miss!"));
+                        ShotOutcome::Miss
                     }
-                    MessageType::TurnHost => {
-                        println!(
-                            "{} {} {}",
-                            Cyan.paint("Wait for"),
-                            Yellow.paint(&host_name),
-                            Cyan.paint("to finish turn!"),
-                        );
-
-                        let result: Result<types::MessageType, _> =
-                            deserialize_from(&mut connection, bincode::SizeLimit::Infinite);
-                        match result {
-                            Ok(res) => {
-                                match res {
-                                    MessageType::Hit(id) => {
-                                        let row = id / 10;
-                                        let col = id % 10;
-                                        client.own_board[row][col] = SubField::Hit;
-                                    }
-                                    MessageType::Miss(id) => {
-                                        let row = id / 10;
-                                        let col = id % 10;
-                                        client.own_board[row][col] = SubField::Miss;
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            Err(_) => println!("Did not receive Hit or Miss message.")
+                    MessageType::Sunk(id, name) if id == idx => {
+                        Green.with(|| println!("You sank the {}!", name));
+                        ShotOutcome::Sunk(name)
+                    }
+                    MessageType::Hit(_) | MessageType::Miss(_) | MessageType::Sunk(..) => {
+                        return Err(NetError::Protocol(
+                            "the server answered a shot at another field".to_string()
+                        ))
+                    }
+                    MessageType::Won => {
+                        // the opponent left the lobby in the meantime
+                        Yellow.with(|| println!("Congratulations, you won the game!"));
+                        *winner = Some(ME);
+                        if let Some(ref mut bot) = *bot {
+                            bot.game_over(true);
                         }
-                        model::print_boards(&client);
+                        continue;
                     }
-                    MessageType::Unexpected => {
-                        Red.with(|| println!("Handshake done wrong!"));
+                    MessageType::Quit => return Err(NetError::Disconnected),
+                    _ => {
+                        return Err(NetError::Protocol("expected the answer to a shot".to_string()))
+                    },
+                };
+                engine.apply(idx, &outcome).map_err(|e| {
+                    NetError::Protocol(format!("invalid answer of the server: {:?}", e))
+                })?;
+                if let Some(ref mut bot) = *bot {
+                    bot.record(&engine.player(ME).op_board, idx, &outcome);
+                }
+                answers.push((idx, outcome));
+                model::print_boards(engine.player(ME));
+            }
+            MessageType::RequestBoard => {
+                if let Err(e) = place_ships(engine, bot, rng) {
+                    Red.with(|| println!("The bot failed placing the ships: {}", e));
+                    return net::send(connection, MessageType::Quit);
+                }
+
+                // send board
+                net::send(connection, MessageType::Board(engine.player(ME).own_board.clone()))?;
+            }
+            MessageType::Commit(commitment) => {
+                Cyan.with(|| println!("Both boards stay hidden until the end."));
+                host_commitment = Some(commitment);
+                if let Err(e) = place_ships(engine, bot, rng) {
+                    Red.with(|| println!("The bot failed placing the ships: {}", e));
+                    return net::send(connection, MessageType::Quit);
+                }
+
+                // only send the commitment of the board
+                placement = engine.player(ME).own_board.clone();
+                net::send(
+                    connection,
+                    MessageType::Commit(net::commit::commitment(&placement, &salt))
+                )?;
+            }
+            MessageType::Reveal(host_salt, host_board) => {
+                if let Some(ref commitment) = host_commitment {
+                    let dim = placement.dim();
+                    match net::commit::check_reveal(
+                        commitment, &host_salt, &host_board, dim, &fleet, &answers
+                    ) {
+                        Ok(()) => {
+                            Green.with(|| println!(
+                                "The board of {} matches the commitment, {}",
+                                host_name,
+                                "all answers were honest.",
+                            ));
+                            *revealed = Some(host_board);
+                        },
+                        Err(e) => Red.with(|| println!("{} cheated: {}!", host_name, e)),
+                    }
+                }
+                net::send(connection, MessageType::Reveal(salt.clone(), placement.clone()))?;
+            }
+            MessageType::Text(t) => {
+                Cyan.with(|| println!("{}", t));
+            }
+            MessageType::TurnHost => {
+                println!(
+                    "{} {} {}",
+                    Cyan.paint("Wait for"),
+                    Yellow.paint(host_name),
+                    Cyan.paint("to finish turn!"),
+                );
+                engine.set_turn(HOST);
+
+                // hidden boards: the answer to the shot of the host
+                let mut answer = None;
+                let result = match chat::receive(connection)? {
+                    MessageType::Hit(id) => engine.apply(id, &ShotOutcome::Hit),
+                    MessageType::Miss(id) => engine.apply(id, &ShotOutcome::Miss),
+                    MessageType::Sunk(id, name) => {
+                        Red.with(|| println!("{} sank your {}!", host_name, name));
+                        engine.apply(id, &ShotOutcome::Sunk(name))
                     }
-                    MessageType::Lost => {
-                        Yellow.with(|| println!("You lost the game :("));
+                    MessageType::Shoot(coord) => {
+                        let id = engine.player(ME).own_board.get_index(&coord).ok_or_else(|| {
+                            NetError::Protocol(format!("invalid coordinate '{}'", coord))
+                        })?;
+                        engine.fire(id).map(|outcome| {
+                            if let ShotOutcome::Sunk(ref name) = outcome {
+                                Red.with(|| println!("{} sank your {}!", host_name, name));
+                            }
+                            answer = Some(match outcome {
+                                ShotOutcome::Hit => MessageType::Hit(id),
+                                ShotOutcome::Miss => MessageType::Miss(id),
+                                ShotOutcome::Sunk(name) => MessageType::Sunk(id, name),
+                            });
+                        })
                     }
                     MessageType::Won => {
+                        // the host forfeited the game
                         Yellow.with(|| println!("Congratulations, you won the game!"));
+                        *winner = Some(ME);
+                        if let Some(ref mut bot) = *bot {
+                            bot.game_over(true);
+                        }
+                        Ok(())
                     }
+                    MessageType::Quit => return Err(NetError::Disconnected),
                     _ => {
-                        Red.with(|| println!("Received unexpected packet"));
-                    }
+                        return Err(NetError::Protocol("expected the shot of the host".to_string()))
+                    },
+                };
+                result.map_err(|e| {
+                    NetError::Protocol(format!("invalid shot of the server: {:?}", e))
+                })?;
+                if let Some(answer) = answer {
+                    net::send(connection, answer)?;
                 }
-            },
-            Err(_) => {
-                Red.with(|| println!("Connection dropped..."));
-                break;
-            },
-        };
+                model::print_boards(engine.player(ME));
+            }
+            MessageType::Unexpected => {
+                return Err(NetError::Handshake("the server rejected the login".to_string()));
+            }
+            MessageType::Lost => {
+                Yellow.with(|| println!("You lost the game :("));
+                *winner = Some(HOST);
+                if let Some(ref mut bot) = *bot {
+                    bot.game_over(false);
+                }
+            }
+            MessageType::Won => {
+                Yellow.with(|| println!("Congratulations, you won the game!"));
+                *winner = Some(ME);
+                if let Some(ref mut bot) = *bot {
+                    bot.game_over(true);
+                }
+            }
+            _ => return Err(NetError::Protocol("unexpected message".to_string())),
+        }
+    }
+}
+
+/// Lets the player or the bot place the ships, unless the board is already set
+fn place_ships(
+    engine: &mut GameEngine,
+    bot: &mut Option<Box<dyn Strategy>>,
+    rng: &mut StdRng
+) -> Result<(), String> {
+    let fleet = engine.fleet().to_vec();
+    if let Some(ref mut bot) = *bot {
+        if engine.player(ME).capacity == 0 {
+            let dim = engine.player(ME).own_board.dim();
+            let board = bot.place(dim, &fleet, rng)?;
+            engine.set_board(ME, &board).map_err(|e| e.to_string())?;
+        }
+        return Ok(());
+    }
+
+    model::print_boards(engine.player(ME));
+
+    if engine.player(ME).capacity == 0 {
+        loop {
+            match model::place_ships(engine, ME) {
+                Ok(()) => { break; },
+                Err(model::types::ErrorType::DeadEndHuman) => {
+                    Red.with(|| println!(
+                        "No suitable position left, {}",
+                        "please restart the ship placement.")
+                    );
+                    engine.restart_placement(ME);
+                },
+                Err(_) => {
+                    Red.with(|| println!("Failed placing ships!"));
+                },
+            }
+        }
     }
+    Ok(())
 }
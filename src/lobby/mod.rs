@@ -0,0 +1,425 @@
+// Lobby: a server for many games at once that doesn't play itself. Clients list
+// the rooms, create a room and wait there, or join the room of a waiting player.
+// Every match runs on its own thread with the lobby as the referee: it knows both
+// boards and answers the shots, so boards can't be hidden in the lobby.
+// Towards the clients the lobby acts like the host of a normal game, see client.
+// If the lobby allows spectators, they choose the match to watch by its room and
+// only see both fleets if both players offer REVEAL, see spectate.
+// The messages of both players are read on threads of their own, so that the chat
+// of a player reaches the opponent at any time, see chat.
+use model;
+use model::engine::GameEngine;
+use model::types::{Board, Player, PlayerId, PlayerType, ShipType, ShotOutcome};
+use net::{self, hello, NetError};
+use net::types::MessageType;
+use rand::{Rng, StdRng};
+use spectate::Audience;
+use std::collections::{BTreeMap, VecDeque};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+use term_painter::ToStyle;
+use term_painter::Color::*;
+
+// how long a waiting player may take to answer a ping
+const PING_TIMEOUT_MS: u64 = 5000;
+
+pub struct Lobby {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub board_dim: usize,
+    pub fleet: Vec<ShipType>,
+    // others may watch the matches, see spectate
+    pub spectators: bool,
+    // the source of the seeds of the matches, see model::game_rng
+    pub rng: StdRng,
+}
+
+/// A player waiting in a room for an opponent
+struct Waiting {
+    name: String,
+    stream: TcpStream,
+    // lets spectators see both fleets, see spectate
+    reveal: bool,
+}
+
+/// Everything the connections share
+#[derive(Default)]
+struct State {
+    rooms: BTreeMap<String, Waiting>,
+    // the running matches by room
+    matches: BTreeMap<String, Audience>,
+    // the names of the players that haven't chosen a room yet
+    idle: Vec<String>,
+}
+
+type Shared = Arc<Mutex<State>>;
+
+/// The messages of both players of a match
+struct Inbox {
+    messages: Receiver<(PlayerId, Result<MessageType, NetError>)>,
+    // messages of a player that arrived before the referee asked for them
+    pending: [VecDeque<MessageType>; 2],
+}
+
+/// Opens the lobby and serves clients until the process is stopped
+pub fn run(lobby: Lobby) -> Result<(), NetError> {
+    let address = SocketAddr::new(lobby.ip, lobby.port);
+    let listener = TcpListener::bind(address)
+        .map_err(|e| NetError::Io(format!("could not listen on {}", address), e))?;
+    Yellow.with(|| println!("The lobby is open on {}.", address));
+
+    let rules = hello::Rules {
+        dim: lobby.board_dim,
+        fleet: lobby.fleet,
+        turns: hello::Turns::Alternating,
+        hidden: false,
+    };
+    let state = Shared::default();
+    let spectators = lobby.spectators;
+    let mut rng = lobby.rng;
+
+    for conn in listener.incoming() {
+        let stream = match conn {
+            Ok(stream) => stream,
+            Err(e) => {
+                Red.with(|| println!("Could not accept a connection: {}", e));
+                continue;
+            },
+        };
+        let rules = rules.clone();
+        let state = state.clone();
+        // drawn for every connection, a match uses the seed of the player that joined
+        let seed = rng.gen();
+        thread::spawn(move || {
+            let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+            if let Err(e) = welcome(stream, &rules, spectators, seed, &state) {
+                Red.with(|| println!("{}: {}", peer, e));
+            }
+        });
+    }
+    Ok(())
+}
+
+fn lock(state: &Shared) -> MutexGuard<'_, State> {
+    // the state stays consistent even if a thread panicked while holding it
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Greets a new client and lets it look for an opponent or watch a match
+fn welcome(
+    mut stream: TcpStream,
+    rules: &hello::Rules,
+    spectators: bool,
+    seed: u64,
+    state: &Shared
+) -> Result<(), NetError> {
+    let mut capabilities = vec![hello::LOBBY];
+    if spectators {
+        capabilities.push(hello::SPECTATORS);
+        capabilities.push(hello::REVEAL);
+    }
+    let lobby_hello = hello::Hello::new("Lobby", Some(rules.clone()), &capabilities);
+    let (client_hello, capabilities) = hello::greet(&mut stream, &lobby_hello)?;
+    let reveal = capabilities.iter().any(|c| c == hello::REVEAL);
+    net::send(
+        &mut stream,
+        MessageType::Welcome(
+            "Welcome to the lobby! Create a room or join one.".to_string(),
+            capabilities,
+        )
+    )?;
+    if client_hello.spectator {
+        return spectate(stream, &client_hello.name, state);
+    }
+
+    let name = client_hello.name;
+    println!("{} entered the lobby.", name);
+    lock(state).idle.push(name.clone());
+    let chosen = choose_room(&mut stream, &name, reveal, state);
+    {
+        let mut state = lock(state);
+        if let Some(pos) = state.idle.iter().position(|n| *n == name) {
+            state.idle.remove(pos);
+        }
+    }
+
+    match chosen {
+        // the match runs on the thread of the player that joined
+        Ok(Some((room, opponent))) => {
+            // the lobby knows both boards, the spectators only if both players agree
+            let audience = Audience::default();
+            if reveal && opponent.reveal {
+                audience.reveal();
+            }
+            lock(state).matches.insert(room.clone(), audience.clone());
+            let (mut rng, seed) = model::game_rng(Some(seed));
+            println!("The match in room '{}' uses the seed {}.", room, seed);
+            let players = [(opponent.name, opponent.stream), (name, stream)];
+            play_match(rules, players, &audience, &mut rng);
+            lock(state).matches.remove(&room);
+            audience.close();
+            Ok(())
+        },
+        // waiting in a room or gone
+        Ok(None) => Ok(()),
+        Err(e) => {
+            let _ = stream.shutdown(Shutdown::Both);
+            Err(e)
+        },
+    }
+}
+
+/// Answers the requests of a client in the lobby until it waits in a room, joins
+/// one or leaves. Returns the joined room and its waiting player.
+fn choose_room(
+    stream: &mut TcpStream,
+    name: &str,
+    reveal: bool,
+    state: &Shared
+) -> Result<Option<(String, Waiting)>, NetError> {
+    loop {
+        match net::receive(stream)? {
+            MessageType::List => {
+                let rooms = {
+                    let state = lock(state);
+                    let rooms = state.rooms.iter()
+                        .map(|(room, waiting)| (room.clone(), waiting.name.clone()))
+                        .collect();
+                    MessageType::Rooms(rooms, state.idle.clone())
+                };
+                net::send(stream, rooms)?;
+            },
+            MessageType::Create(room) => {
+                let mut state = lock(state);
+                if room.trim().is_empty() {
+                    net::send(stream, MessageType::Refused("rooms need a name".to_string()))?;
+                } else if state.rooms.contains_key(&room) || state.matches.contains_key(&room) {
+                    let reason = format!("the room '{}' already exists", room);
+                    net::send(stream, MessageType::Refused(reason))?;
+                } else {
+                    let waiting = Waiting {
+                        name: name.to_string(),
+                        stream: stream.try_clone()?,
+                        reveal: reveal,
+                    };
+                    state.rooms.insert(room.clone(), waiting);
+                    let text = format!("Waiting for an opponent in room '{}'...", room);
+                    net::send(stream, MessageType::Text(text))?;
+                    println!("{} waits in room '{}'.", name, room);
+                    return Ok(None);
+                }
+            },
+            MessageType::Join(room) => {
+                let waiting = lock(state).rooms.remove(&room);
+                match waiting {
+                    Some(mut waiting) => {
+                        // the waiting player may have left in the meantime
+                        if present(&mut waiting.stream) {
+                            return Ok(Some((room, waiting)));
+                        }
+                        println!("{} left room '{}'.", waiting.name, room);
+                        let reason = format!("{} left the room '{}'", waiting.name, room);
+                        net::send(stream, MessageType::Refused(reason))?;
+                    },
+                    None => {
+                        let reason = format!("there is no room '{}'", room);
+                        net::send(stream, MessageType::Refused(reason))?;
+                    },
+                }
+            },
+            MessageType::Quit => {
+                println!("{} left the lobby.", name);
+                return Ok(None);
+            },
+            _ => return Err(NetError::Protocol("expected a request for the lobby".to_string())),
+        }
+    }
+}
+
+/// Lets a spectator watch the match in the room of its choice
+fn spectate(mut stream: TcpStream, name: &str, state: &Shared) -> Result<(), NetError> {
+    let room = match net::receive(&mut stream)? {
+        MessageType::Watch(room) => room,
+        _ => return Err(NetError::Protocol("expected the room to watch".to_string())),
+    };
+    let audience = lock(state).matches.get(&room).cloned();
+    match audience {
+        Some(audience) => {
+            println!("{} watches the match in room '{}'.", name, room);
+            audience.join(stream)
+        },
+        None => {
+            let reason = format!("there is no match in room '{}'", room);
+            net::send(&mut stream, MessageType::Refused(reason))
+        },
+    }
+}
+
+/// Whether the waiting player still answers
+fn present(stream: &mut TcpStream) -> bool {
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(PING_TIMEOUT_MS)));
+    let answer = net::send(stream, MessageType::Ping).and_then(|_| net::receive(stream));
+    let _ = stream.set_read_timeout(None);
+    match answer {
+        Ok(MessageType::Ping) => true,
+        _ => {
+            let _ = stream.shutdown(Shutdown::Both);
+            false
+        },
+    }
+}
+
+/// Plays a match between two players and tells them the result
+fn play_match(
+    rules: &hello::Rules,
+    players: [(String, TcpStream); 2],
+    audience: &Audience,
+    rng: &mut StdRng
+) {
+    let [(first, first_stream), (second, second_stream)] = players;
+    println!("{} plays against {}.", first, second);
+    let mut streams = [first_stream, second_stream];
+    let player = |name: &str| Player {
+        own_board: Board::init(rules.dim),
+        op_board: Board::init(rules.dim),
+        player_type: PlayerType::Human,
+        name: name.to_string(),
+        capacity: 0,
+    };
+    let mut engine = GameEngine::new(player(&first), player(&second), rules.fleet.clone());
+
+    if let Err((id, e)) = referee(&mut engine, &mut streams, audience, rng) {
+        println!("{} forfeits the match: {}", engine.player(id).name, e);
+        engine.resign(id);
+    }
+    audience.show(&engine);
+
+    for &id in &[PlayerId::One, PlayerId::Two] {
+        let stream = &mut streams[id.index()];
+        let result = if engine.winner() == Some(id) { MessageType::Won } else { MessageType::Lost };
+        let _ = net::send(stream, result);
+        let _ = net::send(stream, MessageType::Quit);
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+    if let Some(id) = engine.winner() {
+        println!(
+            "{} won against {}.",
+            engine.player(id).name, engine.player(id.other()).name
+        );
+    }
+}
+
+/// Runs the match until there is a winner.
+/// Fails with the player whose connection failed or who broke the rules.
+fn referee(
+    engine: &mut GameEngine,
+    streams: &mut [TcpStream; 2],
+    audience: &Audience,
+    rng: &mut StdRng
+) -> Result<(), (PlayerId, NetError)> {
+    let ids = [PlayerId::One, PlayerId::Two];
+    let mut inbox = listen(streams)?;
+
+    // both players place their ships at the same time
+    for &id in &ids {
+        let opponent = engine.player(id.other()).name.clone();
+        net::send(&mut streams[id.index()], MessageType::Start(opponent)).map_err(|e| (id, e))?;
+        net::send(&mut streams[id.index()], MessageType::RequestBoard).map_err(|e| (id, e))?;
+    }
+    for &id in &ids {
+        let board = match receive(engine, streams, &mut inbox, id)? {
+            MessageType::Board(board) => board,
+            MessageType::Quit => return Err((id, NetError::Disconnected)),
+            _ => return Err((id, NetError::Protocol("expected the board".to_string()))),
+        };
+        if let Err(e) = engine.set_board(id, &board) {
+            let location = e.field()
+                .map(|idx| format!("{}: ", board.coordinate(idx)))
+                .unwrap_or_default();
+            let e = NetError::Protocol(format!("invalid board: {}{}", location, e));
+            return Err((id, e));
+        }
+    }
+
+    engine.set_turn(if rng.gen() { PlayerId::One } else { PlayerId::Two });
+    audience.show(engine);
+    while engine.winner().is_none() {
+        let shooter = engine.current_turn();
+        let defender = shooter.other();
+        net::send(&mut streams[defender.index()], MessageType::TurnHost)
+            .map_err(|e| (defender, e))?;
+        net::send(&mut streams[shooter.index()], MessageType::RequestCoord)
+            .map_err(|e| (shooter, e))?;
+
+        let coordinate = match receive(engine, streams, &mut inbox, shooter)? {
+            MessageType::Shoot(coord) => coord,
+            MessageType::Quit => return Err((shooter, NetError::Disconnected)),
+            _ => return Err((shooter, NetError::Protocol("expected a shot".to_string()))),
+        };
+        let idx = engine.player(defender).own_board.get_index(&coordinate).ok_or_else(|| {
+            (shooter, NetError::Protocol(format!("invalid coordinate '{}'", coordinate)))
+        })?;
+        let outcome = engine.fire(idx).map_err(|e| {
+            (shooter, NetError::Protocol(format!("invalid shot: {:?}", e)))
+        })?;
+        audience.fired(engine);
+
+        // the shooter learns the outcome, the defender where the shot went
+        for &id in &[shooter, defender] {
+            let msg = match outcome {
+                ShotOutcome::Hit => MessageType::Hit(idx),
+                ShotOutcome::Miss => MessageType::Miss(idx),
+                ShotOutcome::Sunk(ref name) => MessageType::Sunk(idx, name.clone()),
+            };
+            net::send(&mut streams[id.index()], msg).map_err(|e| (id, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the messages of both players on threads of their own
+fn listen(streams: &[TcpStream; 2]) -> Result<Inbox, (PlayerId, NetError)> {
+    let (tx, rx) = mpsc::channel();
+    for &id in &[PlayerId::One, PlayerId::Two] {
+        let mut stream = streams[id.index()].try_clone().map_err(|e| (id, NetError::from(e)))?;
+        let messages = tx.clone();
+        thread::spawn(move || loop {
+            let received = net::receive(&mut stream);
+            let failed = received.is_err();
+            if messages.send((id, received)).is_err() || failed {
+                break;
+            }
+        });
+    }
+    Ok(Inbox { messages: rx, pending: [VecDeque::new(), VecDeque::new()] })
+}
+
+/// Waits for the next message of the given player, meanwhile the chat of both
+/// players is passed on. A player whose connection fails or who leaves loses,
+/// even while it's the opponent's turn.
+fn receive(
+    engine: &GameEngine,
+    streams: &mut [TcpStream; 2],
+    inbox: &mut Inbox,
+    id: PlayerId
+) -> Result<MessageType, (PlayerId, NetError)> {
+    if let Some(msg) = inbox.pending[id.index()].pop_front() {
+        return Ok(msg);
+    }
+    loop {
+        let (from, received) = inbox.messages.recv().map_err(|_| (id, NetError::Disconnected))?;
+        match received.map_err(|e| (from, e))? {
+            MessageType::Chat(_, text) => {
+                let to = from.other();
+                let msg = MessageType::Chat(engine.player(from).name.clone(), text);
+                net::send(&mut streams[to.index()], msg).map_err(|e| (to, e))?;
+            },
+            MessageType::Quit if from != id => return Err((from, NetError::Disconnected)),
+            msg if from == id => return Ok(msg),
+            msg => inbox.pending[from.index()].push_back(msg),
+        }
+    }
+}
@@ -1,25 +1,42 @@
 extern crate bincode;
 #[macro_use]
 extern crate clap;
+extern crate crossterm;
 extern crate ctrlc;
 extern crate rand;
 #[macro_use]
 extern crate serde_derive;
 extern crate term_painter;
 
+mod bot;
+mod chat;
 mod client;
+mod lobby;
 mod model;
 mod net;
 mod server;
+mod simulate;
+mod spectate;
 mod util;
 mod view;
 
-use clap::AppSettings;
-use model::types::{Board};
+use clap::{AppSettings, ArgMatches};
+use model::ai::{self, Ai, Difficulty, Strategy};
+use model::replay::Replay;
+use model::types::{Board, ShipType};
+use rand::StdRng;
+use std::net::{IpAddr, SocketAddr};
+use std::process;
+use std::time::Duration;
 use term_painter::ToStyle;
 use term_painter::Color::*;
 
-const BOARD_SIZE: u8 = 10;
+const BOARD_SIZE: usize = 10;
+const MIN_BOARD_SIZE: usize = 6;
+// the boards leave room for a single letter per column, see Coord::column
+const MAX_BOARD_SIZE: usize = 26;
+const BOT_TIMEOUT_MS: u64 = 5000;
+const SIMULATED_GAMES: usize = 1000;
 
 fn main() {
     ///////////////////////////////////////////////////////////////////////////////////////////////
@@ -29,33 +46,104 @@ fn main() {
         (version: crate_version!())
         (author: crate_authors!())
         (about: "Two player battleship game")
+        (@arg render: --render +takes_value +global possible_value[ascii unicode nocolor]
+            "how the boards are drawn (default: depends on the terminal)")
         (@subcommand server =>
-            (about: "Server instance for the game")
+            (about: "Server instance for the game, played with plain text output")
             (version: crate_version!())
             (author: crate_authors!())
-            (@arg port: +required +takes_value "Connect to <port> on localhost")
+            (@arg port: +required +takes_value "Listen on <port>")
             (@arg name: +required +takes_value "Name of player")
-            (@arg size: -s --size +takes_value
-                "set N as board dimension => N x N [not yet implemented]"
-            )
-            (@arg ships: --ships +takes_value "load ship configuration [not yet implemented]")
+            (@arg bind: --bind +takes_value
+                "address to listen on, 0.0.0.0 or :: for all (default: 127.0.0.1)")
+            (@arg size: -s --size +takes_value "set N as board dimension => N x N (6-26)")
+            (@arg ships: --ships +takes_value "load ship configuration")
             (@arg board: --board +takes_value "load board configuration")
+            (@arg hidden: --hidden "keep both boards secret (commit-reveal protocol)")
+            (@arg spectators: --spectators "let others watch the game, see spectate")
+            (@arg reveal: --reveal requires[spectators] conflicts_with[hidden]
+                "let spectators see both fleets if the opponent agrees with --reveal too")
+            (@arg bot: --bot +takes_value "let the given program play, see the bot module")
+            (@arg timeout: --("bot-timeout") +takes_value "time limit of the bot per move in ms")
+            (@arg seed: --seed +takes_value "seed for all random decisions to reproduce a game")
         )
         (@subcommand client =>
-            (about: "Client instance for the game")
+            (about: "Client instance for the game, played with plain text output")
             (version: crate_version!())
             (author: crate_authors!())
-            (@arg ip: +required +takes_value "Connect to address")
+            (@arg host: +required +takes_value "Connect to host name or address (IPv4 or IPv6)")
             (@arg port: +required +takes_value "Connect to port")
             (@arg name: +required +takes_value "Name of player")
             (@arg board: --board +takes_value "load board configuration")
+            (@arg room: --room +takes_value
+                "join or create this room if the server is a lobby")
+            (@arg reveal: --reveal
+                "let spectators see both fleets if the opponent agrees with --reveal too")
+            (@arg bot: --bot +takes_value "let the given program play, see the bot module")
+            (@arg timeout: --("bot-timeout") +takes_value "time limit of the bot per move in ms")
+            (@arg seed: --seed +takes_value "seed for all random decisions to reproduce a game")
+        )
+        (@subcommand lobby =>
+            (about: "Server for many games between clients, without playing itself")
+            (version: crate_version!())
+            (author: crate_authors!())
+            (@arg port: +required +takes_value "Listen on <port>")
+            (@arg bind: --bind +takes_value
+                "address to listen on, 0.0.0.0 or :: for all (default: 127.0.0.1)")
+            (@arg size: -s --size +takes_value "set N as board dimension => N x N (6-26)")
+            (@arg ships: --ships +takes_value "load ship configuration")
+            (@arg spectators: --spectators "let others watch the matches, see spectate")
+            (@arg seed: --seed +takes_value "seed for the random decisions of all matches")
+        )
+        (@subcommand spectate =>
+            (about: "Watch a game on a server or a match in a lobby")
+            (version: crate_version!())
+            (author: crate_authors!())
+            (@arg host: +required +takes_value "Connect to host name or address (IPv4 or IPv6)")
+            (@arg port: +required +takes_value "Connect to port")
+            (@arg room: --room +takes_value "watch the match in this room if the server is a lobby")
         )
         (@subcommand single =>
-            (about: "Play against the computer")
+            (about: "Play against the computer in the full-screen interface")
             (version: crate_version!())
             (author: crate_authors!())
-            (@arg name: +required +takes_value "Name of player")
+            (@arg name: required_unless[resume] +takes_value "Name of player")
+            (@arg size: -s --size +takes_value "set N as board dimension => N x N (6-26)")
+            (@arg ships: --ships +takes_value "load ship configuration")
             (@arg board: --board +takes_value "load board configuration")
+            (@arg resume: --resume +takes_value
+                conflicts_with[name size ships board difficulty ai bot seed]
+                "continue a saved game")
+            (@arg plain: --plain "use the plain text output instead of the full-screen interface")
+            (@arg difficulty: -d --difficulty +takes_value
+                possible_value[easy normal hard expert]
+                "strength of the computer opponent (default: normal)")
+            (@arg ai: --ai +takes_value conflicts_with[difficulty]
+                possible_value[random hunt parity density]
+                "strategy of the computer opponent")
+            (@arg bot: --bot +takes_value conflicts_with[difficulty ai]
+                "play against the given program, see the bot module")
+            (@arg timeout: --("bot-timeout") +takes_value "time limit of the bot per move in ms")
+            (@arg seed: --seed +takes_value "seed for all random decisions to reproduce a game")
+        )
+        (@subcommand simulate =>
+            (about: "Let two computer players play against each other")
+            (version: crate_version!())
+            (author: crate_authors!())
+            (@arg first: +required possible_value[random hunt parity density]
+                "strategy of the first player")
+            (@arg second: +required possible_value[random hunt parity density]
+                "strategy of the second player")
+            (@arg games: -n --games +takes_value "number of games (default: 1000)")
+            (@arg size: -s --size +takes_value "set N as board dimension => N x N (6-26)")
+            (@arg ships: --ships +takes_value "load ship configuration")
+            (@arg seed: --seed +takes_value "seed for all random decisions to reproduce a game")
+        )
+        (@subcommand replay =>
+            (about: "Step through a recorded game")
+            (version: crate_version!())
+            (author: crate_authors!())
+            (@arg file: +required "replay file written at the end of a game")
         )
     )
         .setting(AppSettings::SubcommandRequired)
@@ -66,6 +154,12 @@ fn main() {
     ///////////////////////////////////////////////////////////////////////////////////////////////
     Yellow.with(|| println!("Welcome to a round of 'battleship'"));
 
+    // --render may be given before or after the subcommand
+    let render = battleship.subcommand().1
+        .and_then(|args| args.value_of("render"))
+        .or(battleship.value_of("render"));
+    view::render::init(render);
+
     match battleship.subcommand() {
         ("server", Some(server_args)) => {
             // required arguments
@@ -73,86 +167,209 @@ fn main() {
             let name = server_args.value_of("name").unwrap().to_string();
 
             // optional arguments
-            let mut size = BOARD_SIZE;
-            if let Some(val) = server_args.value_of("size") {
-                Red.with(|| println!("NOT IMPLEMENTED: Custom board size will be reset to 10x10"));
-                match val.parse::<u8>() {
-                    Ok(val) => size = val,
-                    Err(_) => size = util::read_usize() as u8,
-                }
-            }
+            let size = server_args.value_of("size")
+                .map(|s| validate_size(s))
+                .unwrap_or(BOARD_SIZE);
+
+            let fleet = server_args.value_of("ships")
+                .map(|f| load_fleet(f, size))
+                .unwrap_or(model::default_fleet(size));
 
             let board = server_args.value_of("board")
-                .map(|b| util::read_extern_board(b))
-                .unwrap_or(Board::init());
+                .map(|b| load_board(b, size, &fleet))
+                .unwrap_or(Board::init(size));
 
-            if let Some(_) = server_args.value_of("ships") {
-                Red.with(|| println!("NOT IMPLEMENTED: Custom ship configuration"));
-            }
+            let ip = server_args.value_of("bind")
+                .map(|b| validate_bind(b))
+                .unwrap_or(net::types::LOCALHOST);
 
             println!(
-                "create server-player: '{}' -- connecting to port: {} -- {2}x{2} board",
+                "create server-player: '{}' -- listening on {} -- {2}x{2} board",
                 &name,
-                port,
+                SocketAddr::new(ip, port),
                 size,
             );
 
             // create server
             let server = server::Server {
-                ip: net::types::LOCALHOST,
+                ip: ip,
                 port: port,
                 host_name: name,
                 host_board: board,
                 board_dim: size,
+                fleet: fleet,
+                hidden: server_args.is_present("hidden"),
+                spectators: server_args.is_present("spectators"),
+                reveal: server_args.is_present("reveal"),
+                bot: launch_bot(server_args),
+                rng: game_rng(server_args),
             };
 
-            server::init(server);
+            if let Err(e) = server::init(server) {
+                network_failure(e);
+            }
+        },
+
+        ("lobby", Some(lobby_args)) => {
+            let port = validate_port(lobby_args.value_of("port").unwrap());
+            let ip = lobby_args.value_of("bind")
+                .map(|b| validate_bind(b))
+                .unwrap_or(net::types::LOCALHOST);
+            let size = lobby_args.value_of("size")
+                .map(|s| validate_size(s))
+                .unwrap_or(BOARD_SIZE);
+            let fleet = lobby_args.value_of("ships")
+                .map(|f| load_fleet(f, size))
+                .unwrap_or(model::default_fleet(size));
+
+            let lobby = lobby::Lobby {
+                ip: ip,
+                port: port,
+                board_dim: size,
+                fleet: fleet,
+                spectators: lobby_args.is_present("spectators"),
+                rng: game_rng(lobby_args),
+            };
+            if let Err(e) = lobby::run(lobby) {
+                network_failure(e);
+            }
+        },
+
+        ("spectate", Some(spectate_args)) => {
+            let host = spectate_args.value_of("host").unwrap();
+            let port = validate_port(spectate_args.value_of("port").unwrap());
+            println!("watching the game on {}", net::display_address(host, port));
+            let room = spectate_args.value_of("room");
+            if let Err(e) = spectate::watch("Spectator", host, port, room) {
+                network_failure(e);
+            }
         },
 
         ("client", Some(client_args)) => {
             // required arguments
-            let ip = client_args.value_of("ip").unwrap();
-            // TODO: check for valid ip-address
+            let host = client_args.value_of("host").unwrap();
             let port = validate_port(client_args.value_of("port").unwrap());
             let name = client_args.value_of("name").unwrap();
 
             println!(
-                "create client-player: '{}' -- connecting to {}:{}",
+                "create client-player: '{}' -- connecting to {}",
                 name,
-                ip,
-                port,
+                net::display_address(host, port),
             );
 
-            let board = client_args.value_of("board")
-                .map(|b| util::read_extern_board(b))
-                .unwrap_or(Board::init());
-
+            // the board configuration is loaded as soon as the server
+            // announced the board dimension
             let client = ::model::types::Player {
-                own_board: board.clone(),
-                op_board: Board::init(),
+                own_board: Board::init(BOARD_SIZE),
+                op_board: Board::init(BOARD_SIZE),
                 player_type: ::model::types::PlayerType::Human,
-                capacity: Board::targets(&board),
+                capacity: 0,
                 name: name.to_string(),
             };
 
             // connect to server
-            client::connect(client, ip, port);
+            let bot = launch_bot(client_args);
+            let rng = game_rng(client_args);
+            let options = client::Options {
+                board: client_args.value_of("board"),
+                room: client_args.value_of("room"),
+                reveal: client_args.is_present("reveal"),
+            };
+            if let Err(e) = client::connect(client, options, bot, rng, host, port) {
+                network_failure(e);
+            }
         },
 
         ("single", Some(single_args)) => {
+            if let Some(f) = single_args.value_of("resume") {
+                let (engine, ai, seed) = match model::save::load(f) {
+                    Ok(game) => game,
+                    Err(e) => {
+                        Red.with(|| println!("{}", e));
+                        process::exit(1);
+                    },
+                };
+                println!("--- Single-Player-Mode ---");
+                let (mut rng, _) = model::game_rng(Some(seed));
+                let dim = engine.player(model::types::PlayerId::One).own_board.dim();
+                if !single_args.is_present("plain") && view::supported(dim) {
+                    view::play(engine, Box::new(ai), &mut rng);
+                } else {
+                    model::play_round(engine, Box::new(ai), &mut rng);
+                }
+                println!("");
+                return;
+            }
+
             let name = single_args.value_of("name").unwrap();
+            let size = single_args.value_of("size")
+                .map(|s| validate_size(s))
+                .unwrap_or(BOARD_SIZE);
 
             println!(
-                "create player: '{}'",
-                name
+                "create player: '{}' -- {1}x{1} board",
+                name,
+                size,
             );
 
+            let fleet = single_args.value_of("ships")
+                .map(|f| load_fleet(f, size))
+                .unwrap_or(model::default_fleet(size));
+
             let board = single_args.value_of("board")
-                .map(|b| util::read_extern_board(b))
-                .unwrap_or(Board::init());
+                .map(|b| load_board(b, size, &fleet))
+                .unwrap_or(Board::init(size));
+
+            let strategy: Box<dyn Strategy> = match single_args.value_of("ai") {
+                Some(name) => ai::strategy(name, &fleet).expect("unknown strategy"),
+                None if single_args.is_present("bot") => launch_bot(single_args).unwrap(),
+                None => {
+                    let difficulty = value_t!(single_args, "difficulty", Difficulty)
+                        .unwrap_or(Difficulty::Normal);
+                    Box::new(Ai::new(difficulty, &fleet))
+                },
+            };
 
             println!("--- Single-Player-Mode ---");
-            model::start_round(name.to_string(), board);
+            let mut rng = game_rng(single_args);
+            if !single_args.is_present("plain") && view::supported(size) {
+                let engine = model::single_round(name.to_string(), board, fleet, &*strategy);
+                view::play(engine, strategy, &mut rng);
+            } else {
+                model::start_round(name.to_string(), board, fleet, strategy, &mut rng);
+            }
+        },
+
+        ("simulate", Some(simulate_args)) => {
+            let size = simulate_args.value_of("size")
+                .map(|s| validate_size(s))
+                .unwrap_or(BOARD_SIZE);
+
+            let fleet = simulate_args.value_of("ships")
+                .map(|f| load_fleet(f, size))
+                .unwrap_or(model::default_fleet(size));
+
+            let games = value_t!(simulate_args, "games", usize).unwrap_or(SIMULATED_GAMES);
+            let mut rng = game_rng(simulate_args);
+
+            simulate::run(
+                simulate_args.value_of("first").unwrap(),
+                simulate_args.value_of("second").unwrap(),
+                games,
+                size,
+                &fleet,
+                &mut rng,
+            );
+        },
+
+        ("replay", Some(replay_args)) => {
+            match Replay::load(replay_args.value_of("file").unwrap()) {
+                Ok(replay) => replay.show(),
+                Err(e) => {
+                    Red.with(|| println!("{}", e));
+                    process::exit(1);
+                },
+            }
         },
         _ => unimplemented!()
     }
@@ -160,6 +377,67 @@ fn main() {
     println!("");
 }
 
+/// Load ship configuration
+/// Exits if the configuration is invalid for the board dimension
+fn load_fleet(f: &str, size: usize) -> Vec<ShipType> {
+    let fleet = util::read_extern_ships(f)
+        .and_then(|fleet| model::valid_fleet(&fleet, size).map(|_| fleet));
+
+    match fleet {
+        Ok(fleet) => fleet,
+        Err(e) => {
+            Red.with(|| println!("Invalid ship configuration '{}': {}", f, e));
+            process::exit(1);
+        },
+    }
+}
+
+/// Report a network error and exit with its code
+fn network_failure(e: net::NetError) -> ! {
+    Red.with(|| println!("Network error: {}", e));
+    process::exit(e.exit_code());
+}
+
+/// Load board configuration
+/// Exits if the configuration violates the placement rules
+fn load_board(f: &str, size: usize, fleet: &[ShipType]) -> Board {
+    match util::read_extern_board(f, size, fleet) {
+        Ok(board) => board,
+        Err(e) => {
+            Red.with(|| println!("Invalid board configuration '{}': {}", f, e));
+            process::exit(1);
+        },
+    }
+}
+
+/// Create the random number generator from --seed and log the seed
+fn game_rng(args: &ArgMatches) -> StdRng {
+    let seed = args.value_of("seed").map(|s| match s.parse::<u64>() {
+        Ok(seed) => seed,
+        Err(_) => {
+            Red.with(|| println!("Invalid seed '{}', please use a number.", s));
+            process::exit(1);
+        },
+    });
+    let (rng, seed) = model::game_rng(seed);
+    println!("Seed: {} (reproduce the game with --seed {})", seed, seed);
+    rng
+}
+
+/// Launch the external bot given with --bot, if any
+/// Exits if the bot can't be started
+fn launch_bot(args: &ArgMatches) -> Option<Box<dyn Strategy>> {
+    let command = args.value_of("bot")?;
+    let timeout = value_t!(args, "timeout", u64).unwrap_or(BOT_TIMEOUT_MS);
+    match bot::Bot::launch(command, Duration::from_millis(timeout)) {
+        Ok(bot) => Some(Box::new(bot)),
+        Err(e) => {
+            Red.with(|| println!("Invalid bot: {}", e));
+            process::exit(1);
+        },
+    }
+}
+
 /// Validate port
 /// Only allow usage of ports 1024 up to 65535
 fn validate_port(p: &str) -> u16 {
@@ -182,6 +460,33 @@ fn validate_port(p: &str) -> u16 {
     port
 }
 
+/// Validate the address the server listens on, IPv6 addresses may be given in brackets
+fn validate_bind(b: &str) -> IpAddr {
+    match b.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => ip,
+        Err(_) => {
+            Red.with(|| println!("Invalid address '{}', please use an IPv4 or IPv6 address.", b));
+            process::exit(1);
+        },
+    }
+}
+
+/// Validate board dimension
+/// Only allow boards from 6 x 6 up to 26 x 26 (one letter per column)
+fn validate_size(s: &str) -> usize {
+    let mut size = s.parse::<usize>().unwrap_or(0);
+
+    while size < MIN_BOARD_SIZE || size > MAX_BOARD_SIZE {
+        println!(
+            "Please enter a valid board dimension ({}-{}): ",
+            MIN_BOARD_SIZE,
+            MAX_BOARD_SIZE,
+        );
+        size = util::read_usize();
+    }
+    size
+}
+
 /// Validate port
 /// Only allow usage of ports from 1024 up to 65535
 /// For clap_app! usage if someone knew how to add this to the macro-call...
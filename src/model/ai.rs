@@ -0,0 +1,345 @@
+// Computer opponents for the single-player mode.
+// An AI only sees its view of the opponent's board (hits and misses) and
+// the names of the ships it has sunk - just like a human player.
+use model::{next_field, random_board};
+use model::types::{Board, PlayerType, ShipType, ShotOutcome, SubField};
+use rand::{Rng, StdRng};
+use std::str::FromStr;
+
+/// A computer player: decides where to place its ships and where to fire.
+/// A strategy that returns an error forfeits the game.
+pub trait Strategy {
+    /// The kind of player following the strategy
+    fn player_type(&self) -> PlayerType {
+        PlayerType::AI
+    }
+
+    /// Places the given fleet on a board of the given dimension.
+    /// The ships are placed at random unless the strategy knows better.
+    fn place(&mut self, dim: usize, fleet: &[ShipType], rng: &mut StdRng) -> Result<Board, String> {
+        random_board(dim, fleet, rng)
+    }
+
+    /// Chooses the field to fire at, given the view of the opponent's board
+    fn target(&mut self, board: &Board, rng: &mut StdRng) -> Result<usize, String>;
+
+    /// Learns the outcome of the shot at the given field.
+    /// The board shows the result of the shot already.
+    fn record(&mut self, _board: &Board, _idx: usize, _outcome: &ShotOutcome) {}
+
+    /// Learns whether the game was won
+    fn game_over(&mut self, _won: bool) {}
+
+    /// Returns the built-in AI behind the strategy, if any.
+    /// Only games against the built-in AI can be saved.
+    fn as_ai(&self) -> Option<&Ai> {
+        None
+    }
+}
+
+/// Returns the built-in strategy with the given name:
+/// "random", "hunt", "parity" or "density" (the difficulties easy to expert)
+pub fn strategy(name: &str, fleet: &[ShipType]) -> Option<Box<dyn Strategy>> {
+    let difficulty = match name {
+        "random" => Difficulty::Easy,
+        "hunt" => Difficulty::Normal,
+        "parity" => Difficulty::Hard,
+        "density" => Difficulty::Expert,
+        _ => return None,
+    };
+    Some(Box::new(Ai::new(difficulty, fleet)))
+}
+
+/// How hard the computer opponent plays
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Difficulty {
+    // fires at random
+    Easy,
+    // fires around its hits, otherwise at random
+    Normal,
+    // follows lines of hits and hunts on a checkerboard pattern
+    Hard,
+    // fires where the remaining ships most likely are, see `Ai::density`
+    Expert,
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            "expert" => Ok(Difficulty::Expert),
+            _ => Err(format!("unknown difficulty '{}'", s)),
+        }
+    }
+}
+
+/// The built-in computer opponent, one strategy per difficulty
+#[derive(Serialize, Deserialize)]
+pub struct Ai {
+    difficulty: Difficulty,
+    fleet: Vec<ShipType>,
+    // name and fields of every ship sunk so far
+    sunk: Vec<(String, Vec<usize>)>,
+}
+
+impl Ai {
+    pub fn new(difficulty: Difficulty, fleet: &[ShipType]) -> Ai {
+        Ai {
+            difficulty: difficulty,
+            fleet: fleet.to_vec(),
+            sunk: Vec::new(),
+        }
+    }
+
+    /// Returns the hits that don't belong to a sunk ship
+    fn open_hits(&self, board: &Board) -> Vec<usize> {
+        (0..board.size())
+            .filter(|&i| board.get(i) == SubField::Hit && !self.is_sunk(i))
+            .collect()
+    }
+
+    /// Returns whether the field belongs to a sunk ship
+    fn is_sunk(&self, idx: usize) -> bool {
+        self.sunk.iter().any(|&(_, ref fields)| fields.contains(&idx))
+    }
+
+    /// Returns the sizes of all ships that are still afloat
+    fn remaining(&self) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        for ship in &self.fleet {
+            let sunk = self.sunk.iter().filter(|&&(ref name, _)| *name == ship.name).count();
+            for _ in sunk..ship.amount {
+                sizes.push(ship.size);
+            }
+        }
+        sizes
+    }
+
+    /// Continues a line of at least two hits at one of its ends, or fires
+    /// around a single hit. Returns None if there are no open hits.
+    fn follow_line(&self, board: &Board, rng: &mut StdRng) -> Option<usize> {
+        let dim = board.dim();
+        let sunk: Vec<usize> = self.sunk.iter().flat_map(|s| s.1.clone()).collect();
+        let mut done = Vec::new();
+
+        for hit in self.open_hits(board) {
+            if done.contains(&hit) {
+                continue;
+            }
+            let group = hit_group(board, hit, &sunk);
+            done.extend_from_slice(&group);
+
+            let mut candidates = Vec::new();
+            if group.len() >= 2 {
+                let (first, last) = (group[0], group[group.len() - 1]);
+                if first / dim == last / dim {
+                    // horizontal line
+                    if first % dim > 0 {
+                        candidates.push(first - 1);
+                    }
+                    if last % dim + 1 < dim {
+                        candidates.push(last + 1);
+                    }
+                } else {
+                    // vertical line
+                    if first >= dim {
+                        candidates.push(first - dim);
+                    }
+                    if last + dim < board.size() {
+                        candidates.push(last + dim);
+                    }
+                }
+            } else {
+                candidates = board.neighbours(hit);
+            }
+
+            candidates.retain(|&i| board.get(i) == SubField::Water);
+            if let Some(&target) = rng.choose(&candidates) {
+                return Some(target);
+            }
+        }
+        None
+    }
+
+    /// Hunts on the fields of a checkerboard pattern whose spacing is the size
+    /// of the smallest ship left, so that no ship can slip through.
+    fn parity(&self, board: &Board, rng: &mut StdRng) -> usize {
+        let dim = board.dim();
+        let spacing = self.remaining().into_iter().min().unwrap_or(1).max(1);
+        let on_pattern = |i: usize| (i / dim + i % dim) % spacing == 0;
+        if (0..board.size()).any(|i| board.get(i) == SubField::Water && on_pattern(i)) {
+            random_field(board, on_pattern, rng)
+        } else {
+            random_field(board, |_| true, rng)
+        }
+    }
+
+    /// Counts for every field how many legal placements of the remaining ships
+    /// cover it and fires at the field with the highest count. As long as there
+    /// are open hits only placements covering them are taken into account.
+    fn density(&self, board: &Board, rng: &mut StdRng) -> usize {
+        let counts = self.placement_counts(board);
+        let best = (0..board.size())
+            .filter(|&i| board.get(i) == SubField::Water)
+            .map(|i| counts[i])
+            .max()
+            .unwrap_or(0);
+        if best == 0 {
+            return random_field(board, |_| true, rng);
+        }
+        random_field(board, |i| counts[i] == best, rng)
+    }
+
+    /// Returns the weighted number of legal placements of the remaining ships
+    /// covering each field. A placement is legal if it covers neither a miss nor
+    /// a sunk ship and doesn't touch a sunk ship or a hit it doesn't cover.
+    fn placement_counts(&self, board: &Board) -> Vec<usize> {
+        let open_hits = self.open_hits(board);
+
+        // misses, sunk ships and their surroundings can't be part of a ship
+        let mut blocked: Vec<bool> = (0..board.size())
+            .map(|i| board.get(i) == SubField::Miss)
+            .collect();
+        for &(_, ref fields) in &self.sunk {
+            for &i in fields {
+                blocked[i] = true;
+                for n in surrounding(board, i) {
+                    blocked[n] = true;
+                }
+            }
+        }
+
+        let mut counts = vec![0; board.size()];
+        for size in self.remaining() {
+            for start in 0..board.size() {
+                for ori in &["h", "v"] {
+                    // a ship of size 1 has just one placement
+                    if size == 1 && *ori == "v" {
+                        continue;
+                    }
+                    let fields = match line(board, start, size, ori) {
+                        Some(fields) => fields,
+                        None => continue,
+                    };
+                    if fields.iter().any(|&i| blocked[i]) {
+                        continue;
+                    }
+                    // ships don't touch each other, so every hit next to the
+                    // placement has to be part of it
+                    let touches_hit = fields.iter()
+                        .flat_map(|&i| surrounding(board, i))
+                        .any(|n| board.get(n) == SubField::Hit && !fields.contains(&n));
+                    if touches_hit {
+                        continue;
+                    }
+
+                    let covered = fields.iter().filter(|i| open_hits.contains(i)).count();
+                    let weight = if open_hits.is_empty() { 1 } else { covered };
+                    for &i in &fields {
+                        counts[i] += weight;
+                    }
+                }
+            }
+        }
+        counts
+    }
+}
+
+impl Strategy for Ai {
+    fn target(&mut self, board: &Board, rng: &mut StdRng) -> Result<usize, String> {
+        Ok(match self.difficulty {
+            Difficulty::Easy => random_field(board, |_| true, rng),
+            Difficulty::Normal => hunt_target(board, rng),
+            Difficulty::Hard => match self.follow_line(board, rng) {
+                Some(idx) => idx,
+                None => self.parity(board, rng),
+            },
+            Difficulty::Expert => self.density(board, rng),
+        })
+    }
+
+    fn record(&mut self, board: &Board, idx: usize, outcome: &ShotOutcome) {
+        if let ShotOutcome::Sunk(ref name) = *outcome {
+            // Ships don't touch each other, so the connected hits form the sunk ship.
+            self.sunk.push((name.clone(), hit_group(board, idx, &[])));
+        }
+    }
+
+    fn as_ai(&self) -> Option<&Ai> {
+        Some(self)
+    }
+}
+
+/// Fires around the first hit with a free neighbour, otherwise at random
+fn hunt_target(board: &Board, rng: &mut StdRng) -> usize {
+    // Checks the surrounding of a hit.
+    for i in 0..board.size() {
+        if board.get(i) == SubField::Hit {
+            let target = board.neighbours(i)
+                .into_iter()
+                .find(|&n| board.get(n) == SubField::Water);
+            if let Some(target) = target {
+                return target;
+            }
+        }
+    }
+    random_field(board, |_| true, rng)
+}
+
+/// Chooses a random untouched field that satisfies the filter.
+/// It shouldn't hit a target twice.
+fn random_field<F: Fn(usize) -> bool>(board: &Board, filter: F, rng: &mut StdRng) -> usize {
+    let vec: Vec<usize> = (0..board.size())
+        .filter(|&i| board.get(i) == SubField::Water && filter(i))
+        .collect();
+    *rng.choose(&vec).unwrap()
+}
+
+/// Returns the connected hits containing the given field, sorted by index.
+/// Fields in `exclude` are skipped.
+fn hit_group(board: &Board, idx: usize, exclude: &[usize]) -> Vec<usize> {
+    let mut group = vec![idx];
+    let mut stack = vec![idx];
+    while let Some(i) = stack.pop() {
+        for n in board.neighbours(i) {
+            if board.get(n) == SubField::Hit && !group.contains(&n) && !exclude.contains(&n) {
+                group.push(n);
+                stack.push(n);
+            }
+        }
+    }
+    group.sort();
+    group
+}
+
+/// Returns the indices of the up to eight fields surrounding the given field
+fn surrounding(board: &Board, idx: usize) -> Vec<usize> {
+    let dim = board.dim() as isize;
+    let (row, col) = (idx as isize / dim, idx as isize % dim);
+    let mut res = Vec::new();
+    for dr in -1..2 {
+        for dc in -1..2 {
+            let (r, c) = (row + dr, col + dc);
+            if (dr, dc) != (0, 0) && r >= 0 && r < dim && c >= 0 && c < dim {
+                res.push((r * dim + c) as usize);
+            }
+        }
+    }
+    res
+}
+
+/// Returns the fields of a ship of the given size starting at `start`,
+/// regardless of the other fields. Returns None if it doesn't fit on the board.
+fn line(board: &Board, start: usize, size: usize, ori: &str) -> Option<Vec<usize>> {
+    let mut fields = vec![start];
+    let mut idx = start;
+    for _ in 1..size {
+        idx = next_field(idx, ori, board.dim())?;
+        fields.push(idx);
+    }
+    Some(fields)
+}
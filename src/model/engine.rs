@@ -0,0 +1,355 @@
+// Game engine: owns both players and enforces the rules of a round.
+// It doesn't perform any I/O, so that it can be driven by the terminal,
+// the network or a bot alike.
+use model::{fleet_capacity, ship_indices, validate_board};
+use model::types::{
+    Board, ErrorType, PlacementError, Player, PlayerId, ShipType, Shot, ShotOutcome, SubField,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize)]
+pub struct GameEngine {
+    players: [Player; 2],
+    fleet: Vec<ShipType>,
+    turn: PlayerId,
+    winner: Option<PlayerId>,
+    // Whether the first shot was fired, so the placement is over.
+    started: bool,
+    // The boards as placed, unless unknown (hidden), and every shot so far.
+    placements: [Option<Board>; 2],
+    shots: Vec<Shot>,
+}
+
+impl GameEngine {
+    /// Creates a new round for the given players and fleet.
+    /// The first player starts unless `set_turn()` says otherwise.
+    pub fn new(first: Player, second: Player, fleet: Vec<ShipType>) -> GameEngine {
+        GameEngine {
+            players: [first, second],
+            fleet: fleet,
+            turn: PlayerId::One,
+            winner: None,
+            started: false,
+            placements: [None, None],
+            shots: Vec::new(),
+        }
+    }
+
+    /// Returns the player with the given id
+    pub fn player(&self, id: PlayerId) -> &Player {
+        &self.players[id.index()]
+    }
+
+    /// Returns the fleet each player has to place
+    pub fn fleet(&self) -> &[ShipType] {
+        &self.fleet
+    }
+
+    /// Returns the player whose turn it is
+    pub fn current_turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Hands the turn to the given player, e.g. to choose the starting player
+    pub fn set_turn(&mut self, id: PlayerId) {
+        self.turn = id;
+    }
+
+    /// Returns the winner once all ships of the other player are sunk
+    pub fn winner(&self) -> Option<PlayerId> {
+        self.winner
+    }
+
+    /// Returns the board of the given player as placed before the first shot,
+    /// if it is known
+    pub fn placement(&self, id: PlayerId) -> Option<&Board> {
+        self.placements[id.index()].as_ref()
+    }
+
+    /// Returns all shots fired so far
+    pub fn shots(&self) -> &[Shot] {
+        &self.shots
+    }
+
+    /// Returns whether every player has placed the fleet
+    pub fn ready(&self) -> bool {
+        self.started || self.players.iter().all(|p| p.capacity == fleet_capacity(&self.fleet))
+    }
+
+    /// Places a ship for the given player, starting at `start` with the
+    /// orientation "h" (rightwards) or "v" (upwards).
+    /// Every ship is placed here, complete boards as well, see `set_board()`.
+    pub fn place(
+        &mut self,
+        id: PlayerId,
+        ship: &ShipType,
+        start: usize,
+        ori: &str
+    ) -> Result<(), ErrorType> {
+        let player = &mut self.players[id.index()];
+        if start >= player.own_board.size() {
+            return Err(ErrorType::InvalidField);
+        }
+        match ship_indices(&player.own_board, start, ship.size, ori) {
+            Some(indices) => {
+                player.own_board.place_ship(ship.clone(), indices);
+                player.capacity += ship.size;
+                Ok(())
+            },
+            None => Err(ErrorType::InvalidField),
+        }
+    }
+
+    /// Removes all ships of the given player to start the placement over
+    pub fn restart_placement(&mut self, id: PlayerId) {
+        let player = &mut self.players[id.index()];
+        player.capacity = 0;
+        player.own_board = Board::init(player.own_board.dim());
+    }
+
+    /// Sets the complete board of the given player after checking it against
+    /// the placement rules. Its ships are placed one by one.
+    pub fn set_board(&mut self, id: PlayerId, board: &Board) -> Result<(), PlacementError> {
+        let dim = self.players[id.index()].own_board.dim();
+        let board = validate_board(board, dim, &self.fleet)?;
+        self.restart_placement(id);
+        for ship in board.ships() {
+            // vertical ships are placed upwards from their lowest field
+            let first = *ship.fields.iter().min().expect("ships have fields");
+            let last = *ship.fields.iter().max().expect("ships have fields");
+            let (start, ori) = if last - first >= dim { (last, "v") } else { (first, "h") };
+            self.place(id, &ship.ship_type, start, ori).map_err(|_| {
+                PlacementError::Collision(start, ship.ship_type.name.clone())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Marks the board of the given player as unknown. Shots at it have to be
+    /// answered by its owner and are applied with `apply()`.
+    pub fn set_hidden(&mut self, id: PlayerId) {
+        self.players[id.index()].capacity = fleet_capacity(&self.fleet);
+    }
+
+    /// The current player fires at the field with the given index.
+    /// Afterwards it's the other player's turn.
+    pub fn fire(&mut self, idx: usize) -> Result<ShotOutcome, ErrorType> {
+        self.check_shot(idx)?;
+        self.start();
+
+        let outcome = {
+            let (attacker, opponent) = self.players_mut();
+            let already_hit = opponent.own_board.get(idx) == SubField::Hit;
+            let outcome = opponent.own_board.fire(idx);
+            attacker.op_board.set(idx, opponent.own_board.get(idx));
+            if !already_hit && outcome != ShotOutcome::Miss {
+                opponent.capacity -= 1;
+            }
+            outcome
+        };
+
+        self.finish_turn(idx, &outcome);
+        Ok(outcome)
+    }
+
+    /// The current player fired at the field with the given index of a hidden
+    /// board and the owner answered with the given outcome.
+    /// Afterwards it's the other player's turn.
+    pub fn apply(&mut self, idx: usize, outcome: &ShotOutcome) -> Result<(), ErrorType> {
+        self.check_shot(idx)?;
+        self.start();
+
+        {
+            let (attacker, opponent) = self.players_mut();
+            if attacker.op_board.get(idx) == SubField::Water {
+                if *outcome == ShotOutcome::Miss {
+                    attacker.op_board.set(idx, SubField::Miss);
+                    opponent.own_board.set(idx, SubField::Miss);
+                } else {
+                    attacker.op_board.set(idx, SubField::Hit);
+                    opponent.own_board.set(idx, SubField::Hit);
+                    opponent.capacity = opponent.capacity.saturating_sub(1);
+                }
+            }
+        }
+
+        self.finish_turn(idx, outcome);
+        Ok(())
+    }
+
+    /// The given player gives up, e.g. after breaking the rules.
+    /// The opponent wins the game.
+    pub fn resign(&mut self, id: PlayerId) {
+        if self.winner.is_none() {
+            self.winner = Some(id.other());
+        }
+    }
+
+    /// Checks whether the current player may fire at the given field
+    fn check_shot(&self, idx: usize) -> Result<(), ErrorType> {
+        if self.winner.is_some() {
+            Err(ErrorType::GameOver)
+        } else if !self.ready() {
+            Err(ErrorType::NotReady)
+        } else if idx >= self.players[self.turn.other().index()].own_board.size() {
+            Err(ErrorType::InvalidField)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Ends the placement with the first shot and keeps the known boards
+    fn start(&mut self) {
+        if !self.started {
+            self.started = true;
+            for (placement, player) in self.placements.iter_mut().zip(self.players.iter()) {
+                if player.own_board.targets() > 0 {
+                    *placement = Some(player.own_board.clone());
+                }
+            }
+        }
+    }
+
+    /// Records the shot, then ends the game if the opponent has no ships left,
+    /// otherwise hands over the turn
+    fn finish_turn(&mut self, idx: usize, outcome: &ShotOutcome) {
+        self.shots.push(Shot {
+            player: self.turn,
+            field: idx,
+            outcome: outcome.clone(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        });
+        if self.players[self.turn.other().index()].capacity == 0 {
+            self.winner = Some(self.turn);
+        } else {
+            self.turn = self.turn.other();
+        }
+    }
+
+    /// Returns the current player and its opponent
+    fn players_mut(&mut self) -> (&mut Player, &mut Player) {
+        let (first, second) = self.players.split_at_mut(1);
+        match self.turn {
+            PlayerId::One => (&mut first[0], &mut second[0]),
+            PlayerId::Two => (&mut second[0], &mut first[0]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::types::PlayerType;
+
+    fn player(name: &str) -> Player {
+        Player {
+            own_board: Board::init(6),
+            op_board: Board::init(6),
+            player_type: PlayerType::Human,
+            capacity: 0,
+            name: name.to_string(),
+        }
+    }
+
+    /// A round on a 6x6 board with a single Boat of size 2
+    fn engine() -> GameEngine {
+        let fleet = vec![ShipType { name: "Boat".to_string(), size: 2, amount: 1 }];
+        GameEngine::new(player("One"), player("Two"), fleet)
+    }
+
+    /// A board with the Boat on the given fields
+    fn board(fields: &[usize]) -> Board {
+        let mut board = Board::init(6);
+        for &idx in fields {
+            board.set(idx, SubField::Ship);
+        }
+        board
+    }
+
+    /// A round where both players placed the Boat in the top left corner
+    fn placed() -> GameEngine {
+        let mut engine = engine();
+        engine.set_board(PlayerId::One, &board(&[0, 1])).unwrap();
+        engine.set_board(PlayerId::Two, &board(&[0, 1])).unwrap();
+        engine
+    }
+
+    #[test]
+    fn shots_wait_for_the_placement() {
+        let mut engine = engine();
+        engine.set_board(PlayerId::One, &board(&[0, 1])).unwrap();
+        assert_eq!(engine.fire(0), Err(ErrorType::NotReady));
+        engine.set_board(PlayerId::Two, &board(&[0, 1])).unwrap();
+        assert_eq!(engine.fire(0), Ok(ShotOutcome::Hit));
+    }
+
+    #[test]
+    fn turns_alternate() {
+        let mut engine = placed();
+        engine.set_turn(PlayerId::Two);
+        assert_eq!(engine.fire(35), Ok(ShotOutcome::Miss));
+        assert_eq!(engine.current_turn(), PlayerId::One);
+        // a hit doesn't grant another shot
+        assert_eq!(engine.fire(0), Ok(ShotOutcome::Hit));
+        assert_eq!(engine.current_turn(), PlayerId::Two);
+        let shooters: Vec<_> = engine.shots().iter().map(|shot| shot.player).collect();
+        assert_eq!(shooters, vec![PlayerId::Two, PlayerId::One]);
+        assert_eq!(engine.player(PlayerId::Two).op_board.get(35), SubField::Miss);
+    }
+
+    #[test]
+    fn no_shots_after_the_game() {
+        let mut engine = placed();
+        assert_eq!(engine.fire(0), Ok(ShotOutcome::Hit));
+        assert_eq!(engine.fire(35), Ok(ShotOutcome::Miss));
+        assert_eq!(engine.fire(1), Ok(ShotOutcome::Sunk("Boat".to_string())));
+        assert_eq!(engine.winner(), Some(PlayerId::One));
+        assert_eq!(engine.fire(2), Err(ErrorType::GameOver));
+        assert_eq!(engine.shots().len(), 3);
+    }
+
+    #[test]
+    fn set_board_replaces_the_ships() {
+        let mut engine = engine();
+        let boat = engine.fleet()[0].clone();
+        engine.place(PlayerId::One, &boat, 30, "h").unwrap();
+        engine.set_board(PlayerId::One, &board(&[4, 10])).unwrap();
+        engine.set_board(PlayerId::One, &board(&[0, 1])).unwrap();
+
+        let player = engine.player(PlayerId::One);
+        assert_eq!(player.capacity, 2);
+        assert_eq!(player.own_board.targets(), 2);
+        assert_eq!(player.own_board.ships().len(), 1);
+        assert_eq!(player.own_board.ships()[0].fields, vec![0, 1]);
+
+        // a rejected board leaves the placed ships alone
+        assert!(engine.set_board(PlayerId::One, &board(&[0])).is_err());
+        assert_eq!(engine.player(PlayerId::One).own_board.targets(), 2);
+    }
+
+    #[test]
+    fn answers_about_hidden_boards() {
+        let mut engine = engine();
+        engine.set_board(PlayerId::One, &board(&[0, 1])).unwrap();
+        engine.set_hidden(PlayerId::Two);
+        assert!(engine.ready());
+
+        engine.apply(7, &ShotOutcome::Hit).unwrap();
+        assert_eq!(engine.player(PlayerId::One).op_board.get(7), SubField::Hit);
+        assert_eq!(engine.player(PlayerId::Two).capacity, 1);
+        assert_eq!(engine.fire(35), Ok(ShotOutcome::Miss));
+
+        // a field that was already hit doesn't count twice
+        engine.apply(7, &ShotOutcome::Hit).unwrap();
+        assert_eq!(engine.player(PlayerId::Two).capacity, 1);
+        engine.fire(34).unwrap();
+
+        engine.apply(8, &ShotOutcome::Sunk("Boat".to_string())).unwrap();
+        assert_eq!(engine.winner(), Some(PlayerId::One));
+        assert!(engine.placement(PlayerId::Two).is_none());
+        assert_eq!(engine.apply(9, &ShotOutcome::Miss), Err(ErrorType::GameOver));
+    }
+}
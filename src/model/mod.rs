@@ -1,47 +1,23 @@
 use util;
 use term_painter::ToStyle;
 use term_painter::Color::*;
-use rand::{thread_rng, Rng};
+use rand::{thread_rng, Rng, SeedableRng, StdRng};
+use std::cmp;
 
+pub mod ai;
+pub mod engine;
+pub mod replay;
+pub mod save;
 pub mod types;
 
-// Game logic (board, initialization, valid move, set, play or finished)
-
-/// Visualization of the boards.
-fn print(board1: &[[types::SubField; 10]; 10], board2: &[[types::SubField; 10]; 10]) {
-    println!("\n{}{}", "------------------ O W N   B O A R D ------------------",
-        "-------------------------- O P P O N E N T ---------------");
+// how often the random placement starts over before it gives up
+const PLACEMENT_ATTEMPTS: usize = 1000;
 
-    let mut cnt = 9;
+// Game logic (board, initialization, valid move, set, play or finished)
 
-    for row in 0..10 {
-        print!("\n{0:<width$}", cnt, width=3);
-        for field in 0..10 {
-            if board1[row][field] == types::SubField::Hit {
-                print!(" {} ", Red.paint(board1[row][field]));
-            } else {
-                print!(" {} ", board1[row][field]);
-            }
-        }
-        print!("        ");
-        print!("{}  ", cnt);
-        for field in 0..10 {
-            if board2[row][field] == types::SubField::Hit {
-                print!(" {} ", Green.paint(board2[row][field]));
-            } else {
-                print!(" {} ", board2[row][field]);
-            }
-        }
-        println!("");
-        cnt -= 1;
-    }
-    println!("");
-    println!("{}{}", "     A    B    C    D    E    F    G    H    I    J",
-        "               A    B    C    D    E    F    G    H    I    J");
-    println!("");
-    println!("{}{}", "----------------------------------------------------",
-        "-------------------------------------------------------------");
-    println!("");
+/// Visualization of the boards, see view::render.
+fn print(board1: &types::Board, board2: &types::Board) {
+    ::view::render::get().print_boards(board1, board2);
 }
 
 /// Print boards of player
@@ -52,246 +28,68 @@ pub fn print_boards(player: &types::Player) {
 
 /// Determines whether the chosen field is a valid one. Considers the
 /// Moore neighborhood because the ships shouldn't collide.
-fn valid_field(player: &types::Player, input: usize, ori: &str) -> bool {
-
-    let mut valid = false;
-
-    let row = input / 10;
-    let col = input % 10;
+/// For the following parts of a ship ("v" = upwards, "h" = rightwards)
+/// the previous part of the same ship is allowed to be occupied.
+fn valid_field(board: &types::Board, input: usize, ori: &str) -> bool {
+
+    let dim = board.dim();
+    let row = input / dim;
+    let col = input % dim;
+
+    // The previous part of the same ship, if any.
+    let prev = match ori {
+        "v" => Some((row + 1, col)),
+        "h" if col > 0 => Some((row, col - 1)),
+        _ => None,
+    };
 
-    match ori {
-        "v" => {
-            // The last part of the ship - top-left corner.
-            if input == 0 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row + 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The last part of the ship - top-right corner.
-            } else if input == 9 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The last part of the ship - top row (except the corners).
-            } else if input < 10 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The left column except the corners.
-            } else if input % 10 == 0 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col + 1] == types::SubField::Water
-                    && player.own_board[row + 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The right column except the corners.
-            } else if input % 10 == 9 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The ordinary rest of the fields. A downwards collision is
-            // allowed since it is the same ship.
-            } else {
-                valid = check_ordinary(&player, row, col, ori);
-            }
-        },
-        "h" => {
-            // The last part of the ship - top-right corner.
-            if input == 9 {
-                if player.own_board[row][col] == types::SubField::Water
-                   && player.own_board[row + 1][col] == types::SubField::Water
-                   && player.own_board[row + 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The last part of the ship - bottom-right corner.
-            } else if input == 99 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The bottom row except the corners.
-            } else if input / 10 == 9 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col - 1] == types::SubField::Water
-                    && player.own_board[row - 1][col + 1 ] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The top row except the corners.
-            } else if input < 10 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The last part of the ship - right column except the corners.
-            } else if input % 10 == 9 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The ordinary rest of the fields. The left-hand side is allowed
-            // to be occupied since it is the same ship.
-            } else {
-                valid = check_ordinary(&player, row, col, ori);
+    for r in row.saturating_sub(1)..cmp::min(row + 2, dim) {
+        for c in col.saturating_sub(1)..cmp::min(col + 2, dim) {
+            if Some((r, c)) == prev {
+                continue;
             }
-        },
-        _ => {
-            // The first part of the ship needs to be fully surrounded by water.
-            // Checks the Moore neighborhood.
-            // The top-left corner.
-            if input == 0 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The top-right corner.
-            } else if input == 9 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The bottom-right corner.
-            } else if input == 99 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The bottom-left corner.
-            } else if input == 90 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The bottom row except the corners.
-            } else if input / 10 == 9 {
-               if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col - 1] == types::SubField::Water
-                    && player.own_board[row - 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The left column except the corners.
-            } else if input % 10 == 0 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col + 1] == types::SubField::Water
-                    && player.own_board[row - 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The right column except the corners.
-            } else if input % 10 == 9 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row - 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                    && player.own_board[row - 1][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The top row except the corners.
-            } else if input < 10 {
-                if player.own_board[row][col] == types::SubField::Water
-                    && player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row][col + 1] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                    && player.own_board[row + 1][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col + 1] == types::SubField::Water
-                {
-                    valid = true;
-                }
-            // The ordinary rest of the fields.
-            } else {
-                valid = check_ordinary(&player, row, col, ori);
+            if board[r][c] != types::SubField::Water {
+                return false;
             }
-        },
+        }
     }
-    valid
+    true
 }
 
-/// Checks the neighborhood of the "ordinary" fields depending on the ship's orientation.
-/// If it is the first part of the ship, the whole moore-neighborhood gets checked.
-/// Otherwise the left-hand respectively bottom field is ignored.
-fn check_ordinary(player: &types::Player, row: usize, col: usize, ori: &str) -> bool {
-
-    if player.own_board[row][col] == types::SubField::Water
-        && player.own_board[row][col + 1] == types::SubField::Water
-        && player.own_board[row - 1][col] == types::SubField::Water
-        && player.own_board[row - 1][col - 1] == types::SubField::Water
-        && player.own_board[row - 1][col + 1] == types::SubField::Water
-        && player.own_board[row + 1][col - 1] == types::SubField::Water
-        && player.own_board[row + 1][col + 1] == types::SubField::Water
-    {
-        match ori {
-            "v" => {
-                if player.own_board[row][col - 1] == types::SubField::Water {
-                    return true;
-                }
-            },
-            "h" => {
-                if player.own_board[row + 1][col] == types::SubField::Water {
-                    return true;
-                }
-            },
-            _ => {
-                if player.own_board[row][col - 1] == types::SubField::Water
-                    && player.own_board[row + 1][col] == types::SubField::Water
-                {
-                    return true;
-                }
-            }
+/// Returns the index of the next part of a ship in the given orientation
+/// ("v" = upwards, "h" = rightwards) or None if it would leave the board.
+fn next_field(idx: usize, ori: &str, dim: usize) -> Option<usize> {
+    match ori {
+        "v" => idx.checked_sub(dim),
+        _ => if (idx + 1) % dim == 0 { None } else { Some(idx + 1) },
+    }
+}
+
+/// Collects the indices a ship of the given size would occupy when starting
+/// at `start` with the given orientation. Returns None if the ship doesn't fit.
+pub fn ship_indices(
+    board: &types::Board,
+    start: usize,
+    size: usize,
+    ori: &str
+) -> Option<Vec<usize>> {
+
+    // The complete Moore neighborhood needs to be free
+    // to place the first part of the ship.
+    if !valid_field(board, start, "") {
+        return None;
+    }
+
+    let mut indices = vec![start];
+    let mut idx = start;
+    for _ in 0..size - 1 {
+        match next_field(idx, ori, board.dim()) {
+            Some(next) if valid_field(board, next, ori) => idx = next,
+            _ => return None,
         }
+        indices.push(idx);
     }
-    false
+    Some(indices)
 }
 
 /// Removes the current rand value from the remaining possibilities.
@@ -307,15 +105,12 @@ fn remove_idx(rand: usize, vec: &mut Vec<usize>) {
 
 /// Random ship placement for the AI.
 fn place_ai(
-    player: &mut types::Player,
+    board: &mut types::Board,
     ship: &types::ShipType,
-    mut vec: &mut Vec<usize>
+    mut vec: &mut Vec<usize>,
+    rng: &mut StdRng
 ) -> Result<(), types::ErrorType> {
 
-    // Vector to collect the indices for the possible ship position.
-    let mut indices = Vec::new();
-
-    let mut rng = thread_rng();
     let mut rand = *rng.choose(&vec).unwrap();
 
     // The complete Moore-neighborhood needs to be free
@@ -326,8 +121,7 @@ fn place_ai(
         // need to restart the whole placement process.
         if vec.len() == 1 {
             return Err(types::ErrorType::DeadEndAI);
-        } else if valid_field(&player, rand, "") {
-            indices.push(rand);
+        } else if valid_field(board, rand, "") {
             remove_idx(rand, &mut vec);
             break;
         // Invalid fields should be removed from vec.
@@ -338,109 +132,95 @@ fn place_ai(
     }
 
     // Random bool to determine the orientation.
-    let ori = rng.gen::<bool>();
-
-    // Vertical
-    if ori {
-        for i in 0..ship.size - 1 {
-
-            // If the result would be negative, checked_sub()
-            // returns None and input is set to 100 by unwrap_or().
-            // In this case, it has to be the last part of the ship
-            // because there's no field above.
-            rand = rand.checked_sub(10).unwrap_or(100);
+    let ori = if rng.gen::<bool>() { "v" } else { "h" };
 
-            if rand == 100 && i != ship.size - 1 || !valid_field(&player, rand, "v") {
-                remove_idx(rand, &mut vec);
-                return Err(types::ErrorType::InvalidField)
-            }
-            indices.push(rand);
-        }
-    } else {
-        for i in 0..ship.size - 1 {
-            rand += 1;
-            // unit position == 9 --> no field to the right
-            if (rand % 10) == 0 && i != ship.size - 1 || !valid_field(&player, rand, "h") {
-                remove_idx(rand, &mut vec);
-                return Err(types::ErrorType::InvalidField)
-            }
-            indices.push(rand);
-        }
-    }
+    let indices = match ship_indices(board, rand, ship.size, ori) {
+        Some(indices) => indices,
+        None => return Err(types::ErrorType::InvalidField),
+    };
 
-    // Places the ships on the board.
-    for i in indices.iter() {
-        let row = *i / 10;
-        let col = *i % 10;
-        player.own_board[row][col] = types::SubField::Ship;
-    }
+    // Places the ship on the board.
+    board.place_ship(ship.clone(), indices);
 
     Ok(())
 }
 
-/// Checks whether there is a remaining position to place the current ship at.
-fn available_space(player: &types::Player, ship: &types::ShipType) -> bool {
-
-    let mut v_cnt = 1;
-    let mut h_cnt = 1;
+/// Places the given ships at random on a new board of the given dimension.
+/// Fails if the ships didn't fit after `PLACEMENT_ATTEMPTS` tries, the fleet is
+/// probably too large for the board then.
+pub fn random_board(
+    dim: usize,
+    ships: &[types::ShipType],
+    rng: &mut StdRng
+) -> Result<types::Board, String> {
+    // Restarts the placement whenever the remaining ships don't fit anymore.
+    'placement: for _ in 0..PLACEMENT_ATTEMPTS {
+        let mut board = types::Board::init(dim);
 
-    for i in 0..100 {
-
-        let mut val = i;
-
-        if valid_field(&player, i, "") {
-            for j in 0..ship.size - 1 {
-                val = val.checked_sub(10).unwrap_or(100);
-                if val == 100 && j != ship.size - 1 || !valid_field(&player, val, "v") {
-                    break;
-                } else { v_cnt += 1; }
-            }
-
-            val = i;
+        // Holds the remaining indices to place a ship at.
+        let mut vec: Vec<usize> = (0..board.size()).collect();
 
-            for j in 0..ship.size - 1 {
-                val += 1;
-                if (val % 10) == 0 && j != ship.size - 1 || !valid_field(&player, val, "h") {
-                    break;
-                } else { h_cnt += 1; }
+        for i in ships.iter() {
+            for _ in 0..i.amount {
+                loop {
+                    match place_ai(&mut board, i, &mut vec, rng) {
+                        Ok(_) => { break; },
+                        Err(types::ErrorType::InvalidField) => {},
+                        Err(_) => { continue 'placement; },
+                    }
+                }
             }
         }
+        return Ok(board);
+    }
+    Err(format!(
+        "The ships could not be placed on the {0}x{0} board in {1} attempts",
+        dim, PLACEMENT_ATTEMPTS,
+    ))
+}
+
+/// Checks whether there is a remaining position to place the current ship at.
+fn available_space(player: &types::Player, ship: &types::ShipType) -> bool {
 
-        if v_cnt == ship.size || h_cnt == ship.size {
+    for i in 0..player.own_board.size() {
+        if ship_indices(&player.own_board, i, ship.size, "v").is_some()
+            || ship_indices(&player.own_board, i, ship.size, "h").is_some()
+        {
             return true
-        } else {
-            v_cnt = 1;
-            h_cnt = 1;
         }
     }
     false
 }
 
 /// The actual placement of the ships.
-fn place(player: &mut types::Player, ship: &types::ShipType) -> Result<(), types::ErrorType> {
+fn place(
+    engine: &mut engine::GameEngine,
+    id: types::PlayerId,
+    ship: &types::ShipType
+) -> Result<(), types::ErrorType> {
 
-    if !available_space(&player, &ship) {
+    if !available_space(engine.player(id), ship) {
         return Err(types::ErrorType::DeadEndHuman)
     }
 
-    // Vector to collect the indices for the possible ship position.
-    let mut indices = Vec::new();
-
-    let input = util::read_string();
-    let mut idx = types::Board::get_index(&input);
+    let idx;
     loop {
-        while idx == 100 {
-            println!("Invalid input, again please.");
-            idx = types::Board::get_index(&util::read_string());
+        let player = engine.player(id);
+        let input = util::read_string();
+        if input.split_whitespace().next() == Some("save") {
+            Red.with(|| println!(
+                "The game can only be saved once all ships are placed, coordinates please."
+            ));
+            continue;
         }
-        // The complete Moore neighborhood needs to be free
-        // to place the first part of the ship.
-        if valid_field(&player, idx, "") {
-            indices.push(idx);
-            break;
-        } else {
-            println!("Invalid input, again please.");
-            idx = types::Board::get_index(&util::read_string());
+        match player.own_board.get_index(&input) {
+            // The complete Moore neighborhood needs to be free
+            // to place the first part of the ship.
+            Some(i) if valid_field(&player.own_board, i, "") => {
+                idx = i;
+                break;
+            },
+            _ => println!("Invalid input, again please."),
         }
     }
 
@@ -461,466 +241,521 @@ fn place(player: &mut types::Player, ship: &types::ShipType) -> Result<(), types
         }
     }
 
-    if ori == "v" {
-        for i in 0..ship.size - 1 {
+    engine.place(id, ship, idx, &ori)
+}
 
-            // If the result would be negative, checked_sub()
-            // returns None and input is set to 100 by unwrap_or().
-            // In this case, it has to be the last part of the ship
-            // because there's no field above.
-            idx = idx.checked_sub(10).unwrap_or(100);
+/// Creates the random number generator all random decisions of a game are based on.
+/// Without a seed a random one is chosen. Returns the seed to reproduce the game.
+pub fn game_rng(seed: Option<u64>) -> (StdRng, u64) {
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+    (StdRng::from_seed(&[seed as usize][..]), seed)
+}
 
-            if idx == 100 && i != ship.size - 1 || !valid_field(&player, idx, &ori) {
-                return Err(types::ErrorType::InvalidField)
-            }
-            indices.push(idx);
+/// Returns the default ships each player needs to place on a board of the
+/// given dimension. The default version for a 10 x 10 board:
+///
+/// #  Class of ship Size
+/// 4   Submarine     2
+/// 3   Destroyer     3
+/// 2   Cruiser       4
+/// 1   Battleship    5
+///
+/// For other dimensions the amounts are scaled by the space the ships may
+/// take, (dim + 1)² as in `valid_fleet`, so that every board is as crowded as
+/// the default one and the ships can still be placed at random. Ships that
+/// don't fit into a row are left out.
+pub fn default_fleet(dim: usize) -> Vec<types::ShipType> {
+    let default = vec![
+        ("Submarine", 2, 4),
+        ("Destroyer", 3, 3),
+        ("Cruiser", 4, 2),
+        ("Battleship", 5, 1),
+    ];
+
+    let scale = ((dim + 1) * (dim + 1)) as f64 / 121.0;
+    default.into_iter()
+        .filter(|&(_, size, _)| size < dim)
+        .map(|(name, size, amount)| types::ShipType {
+            name: name.to_string(),
+            size: size,
+            amount: (amount as f64 * scale).round() as usize,
+        })
+        .filter(|ship| ship.amount > 0)
+        .collect()
+}
+
+/// Returns the amount of fields occupied by all ships of the fleet.
+pub fn fleet_capacity(fleet: &[types::ShipType]) -> usize {
+    fleet.iter().map(|ship| ship.size * ship.amount).sum()
+}
+
+/// Checks whether the fleet can be used on a board of the given dimension.
+/// Every ship needs to fit into a row and, since the ships shouldn't collide,
+/// every ship blocks its own fields plus one extra row and column of water.
+pub fn valid_fleet(fleet: &[types::ShipType], dim: usize) -> Result<(), String> {
+    if fleet.is_empty() {
+        return Err("The fleet contains no ships".to_string());
+    }
+
+    for (i, ship) in fleet.iter().enumerate() {
+        if ship.size == 0 || ship.size > dim {
+            return Err(format!(
+                "The {} of size {} doesn't fit on a {2}x{2} board",
+                ship.name,
+                ship.size,
+                dim,
+            ));
         }
-    } else {
-        for i in 0..ship.size - 1 {
-            idx += 1;
-            if (idx % 10) == 0 && i != ship.size - 1 || !valid_field(&player, idx, &ori) {
-                return Err(types::ErrorType::InvalidField)
-            }
-            indices.push(idx);
+        // every ship has to count, otherwise the fleet could even be sunk without a shot
+        if ship.amount == 0 {
+            return Err(format!("There has to be at least one {}", ship.name));
+        }
+        // sunk ships are told apart by their name
+        if fleet[..i].iter().any(|other| other.name == ship.name) {
+            return Err(format!("The {} is listed more than once", ship.name));
         }
     }
 
-    // Places the ships on the board.
-    for i in indices.iter() {
-        let row = *i / 10;
-        let col = *i % 10;
-        player.own_board[row][col] = types::SubField::Ship;
+    let blocked: usize = fleet.iter().map(|ship| (ship.size + 1) * 2 * ship.amount).sum();
+    if blocked > (dim + 1) * (dim + 1) {
+        return Err(format!("The fleet is too large for a {0}x{0} board", dim));
     }
-
     Ok(())
 }
 
-/// Resets the particular player's board to prepare the (re)placement.
-pub fn restart_placement(p: &mut types::Player) {
-    p.capacity = 0;
-    for i in 0..10 {
-        for j in 0..10 {
-            p.own_board[i][j] = types::SubField::Water;
-        }
+/// Validates a complete board, e.g. an external board configuration or the
+/// board of the opponent, against the placement rules: Every group of
+/// connected ship fields needs to be a straight ship of the fleet and, like
+/// during the placement, the Moore neighborhood of every ship has to be free.
+/// Returns a board knowing its individual ships.
+pub fn validate_board(
+    board: &types::Board,
+    dim: usize,
+    fleet: &[types::ShipType]
+) -> Result<types::Board, types::PlacementError> {
+
+    if board.dim() != dim || board.size() != dim * dim {
+        return Err(types::PlacementError::Dimension(board.dim(), dim));
     }
-}
 
-/// Handles the initial ship placement for each player.
-pub fn place_ships(mut p: &mut types::Player) -> Result<(), types::ErrorType> {
+    let mut remaining: Vec<usize> = fleet.iter().map(|ship| ship.amount).collect();
+    let mut res = types::Board::init(dim);
 
-    // A vector of all the ships each player needs to place.
-    // The default version: #  Class of ship Size
-    //                      4   Submarine     2
-    //                      3   Destroyer     3
-    //                      2   Cruiser       4
-    //                      1   Battleship    5
-    //
-    let s1 = types::ShipType{ name: "Submarine".to_string(), size: 2, amount: 4 };
-    let s2 = types::ShipType{ name: "Destroyer".to_string(), size: 3, amount: 3 };
-    let s3 = types::ShipType{ name: "Cruiser".to_string(), size: 4, amount: 2 };
-    let s4 = types::ShipType{ name: "Battleship".to_string(), size: 5, amount: 1 };
-    let ships = vec![s1, s2, s3, s4];
+    // The groups are ordered by their top-left field.
+    for group in board.ship_groups() {
+        let first = group[0];
+        let size = group.len();
 
-    if p.capacity == 0 && p.name == "AI" {
+        // A ship is placed starting with its left respectively bottom field.
+        let (start, ori) = if group.iter().all(|&i| i / dim == first / dim) {
+            (first, "h")
+        } else if group.iter().all(|&i| i % dim == first % dim) {
+            (group[size - 1], "v")
+        } else {
+            return Err(types::PlacementError::Shape(first));
+        };
 
-        // Holds the remaining indices to place a ship at.
-        let mut vec = Vec::new();
-        for i in 0..100 {
-            vec.push(i);
+        let ship = match (0..fleet.len()).find(|&i| fleet[i].size == size && remaining[i] > 0) {
+            Some(i) => {
+                remaining[i] -= 1;
+                &fleet[i]
+            },
+            None => {
+                return Err(match fleet.iter().find(|ship| ship.size == size) {
+                    Some(ship) => types::PlacementError::TooMany(first, ship.name.clone()),
+                    None => types::PlacementError::UnknownSize(first, size),
+                })
+            },
+        };
+
+        // Places the ship like a player would, so that it has to
+        // keep its distance to the ships placed before.
+        match ship_indices(&res, start, size, ori) {
+            Some(indices) => res.place_ship(ship.clone(), indices),
+            None => return Err(types::PlacementError::Collision(first, ship.name.clone())),
         }
+    }
+
+    for (ship, amount) in fleet.iter().zip(remaining) {
+        if amount > 0 {
+            return Err(types::PlacementError::Missing(ship.name.clone(), amount));
+        }
+    }
+    Ok(res)
+}
+
+/// Handles the initial ship placement of the given player, who needs to
+/// place every ship of the fleet.
+pub fn place_ships(
+    engine: &mut engine::GameEngine,
+    id: types::PlayerId
+) -> Result<(), types::ErrorType> {
+
+    if engine.player(id).capacity == 0 {
 
-        // Asks the AI to place its ships.
+        print_boards(engine.player(id));
+
+        // Asks the human player to place the ships.
+        let ships = engine.fleet().to_vec();
         for i in ships.iter() {
             for _ in 0..i.amount {
                 loop {
-                    match place_ai(&mut p, i, &mut vec) {
+                    println!("{}, please enter the first coordinate for your {:?} ({}{}",
+                        engine.player(id).name, i.name, i.size, " fields).");
+                    match place(engine, id, i) {
                         Ok(_) => { break; },
                         Err(e) => {
                             match e {
-                                types::ErrorType::InvalidField => {},
-                                types::ErrorType::DeadEndAI => { return Err(e) },
+                                types::ErrorType::InvalidField => {
+                                    println!("Invalid position for this ship, {}",
+                                        "please choose another coordinate.");
+                                },
                                 _ => { return Err(e) },
                             }
                         },
                     }
                 }
-                p.capacity += i.size;
-            }
-        }
-    } else {
-        if p.capacity == 0 {
-
-            print_boards(&p);
-
-            // Asks the human player to place the ships.
-            for i in ships.iter() {
-                for _ in 0..i.amount {
-                    loop {
-                        println!("{}, please enter the first coordinate for your {:?} ({}{}",
-                            p.name, i.name, i.size, " fields).");
-                        match place(&mut p, i) {
-                            Ok(_) => { break; },
-                            Err(e) => {
-                                match e {
-                                    types::ErrorType::InvalidField => {
-                                        println!("Invalid position for this ship, {}",
-                                            "please choose another coordinate.");
-                                    },
-                                    _ => { return Err(e) },
-                                }
-                            },
-                        }
-                    }
-                    p.capacity += i.size;
-                    print_boards(&p);
-                }
+                print_boards(engine.player(id));
             }
         }
     }
     Ok(())
 }
 
-/// Reads the coordinates of a field from the user
-/// and returns the corresponding index.
-fn get_input() -> usize {
-
-    // 100 --> invalid
-    let mut input = 100;
-
-    while input == 100 {
-        input = match util::read_string().as_ref() {
-            "A0" => 90, "A1" => 80, "A2" => 70, "A3" => 60, "A4" => 50,
-            "A5" => 40, "A6" => 30, "A7" => 20, "A8" => 10, "A9" => 0,
-            "B0" => 91, "B1" => 81, "B2" => 71, "B3" => 61, "B4" => 51,
-            "B5" => 41, "B6" => 31, "B7" => 21, "B8" => 11, "B9" => 1,
-            "C0" => 92, "C1" => 82, "C2" => 72, "C3" => 62, "C4" => 52,
-            "C5" => 42, "C6" => 32, "C7" => 22, "C8" => 12, "C9" => 2,
-            "D0" => 93, "D1" => 83, "D2" => 73, "D3" => 63, "D4" => 53,
-            "D5" => 43, "D6" => 33, "D7" => 23, "D8" => 13, "D9" => 3,
-            "E0" => 94, "E1" => 84, "E2" => 74, "E3" => 64, "E4" => 54,
-            "E5" => 44, "E6" => 34, "E7" => 24, "E8" => 14, "E9" => 4,
-            "F0" => 95, "F1" => 85, "F2" => 75, "F3" => 65, "F4" => 55,
-            "F5" => 45, "F6" => 35, "F7" => 25, "F8" => 15, "F9" => 5,
-            "G0" => 96, "G1" => 86, "G2" => 76, "G3" => 66, "G4" => 56,
-            "G5" => 46, "G6" => 36, "G7" => 26, "G8" => 16, "G9" => 6,
-            "H0" => 97, "H1" => 87, "H2" => 77, "H3" => 67, "H4" => 57,
-            "H5" => 47, "H6" => 37, "H7" => 27, "H8" => 17, "H9" => 7,
-            "I0" => 98, "I1" => 88, "I2" => 78, "I3" => 68, "I4" => 58,
-            "I5" => 48, "I6" => 38, "I7" => 28, "I8" => 18, "I9" => 8,
-            "J0" => 99, "J1" => 89, "J2" => 79, "J3" => 69, "J4" => 59,
-            "J5" => 49, "J6" => 39, "J7" => 29, "J8" => 19, "J9" => 9,
-            _ => 100,
-        };
-
-        if input == 100 {
-            println!("Invalid field, try again.")
-        }
+/// Checks whether the field at the given index of the opponent's board was
+/// already fired at and prints a note saying whether that shot hit or missed.
+/// The board is left unchanged.
+pub fn repeated_shot(board: &types::Board, idx: usize) {
+    match board.get(idx) {
+        types::SubField::Hit => println!("Already hit."),
+        types::SubField::Miss => println!("Miss - try again (already tried)."),
+        _ => {},
     }
-    input
 }
 
-/// Check if given input is a valid coordinate
-pub fn valid_coordinate(input: &str) -> bool {
-    if input.len() != 2 {
-        return false
-    }
-
-    let lower = input.to_owned().to_lowercase();
-    let mut alpha = false;
-    let mut num = false;
-
-    for c in lower.chars() {
-        if !alpha && !num {
-            if valid_alpha(c) {
-                alpha = true;
-            } else if valid_num(c) {
-                num = true;
-            } else {
-                return false;
-            }
-        } else if !alpha {
-            alpha = valid_alpha(c);
-        } else if !num {
-            num = valid_num(c);
-        } else {
-            return false;
-        }
-    }
-
-    alpha && num
-}
-
-/// Check if given character is of valid alphabetic value
-fn valid_alpha(c: char) -> bool {
-    match c {
-        'a' ... 'j' => true,
-        _ => false,
+/// Prints the outcome of a shot.
+pub fn announce(outcome: &types::ShotOutcome) {
+    match *outcome {
+        types::ShotOutcome::Miss => println!("Miss - try again."),
+        types::ShotOutcome::Hit => println!("Hit!"),
+        types::ShotOutcome::Sunk(_) => println!("Hit and sunk!"),
     }
 }
 
-/// Check if given character is of valid numeric value
-fn valid_num(c: char) -> bool {
-    match c {
-        '0' ... '9' => true,
-        _ => false,
-    }
-}
-
-/// Determines the type of the SubField that got hit
-/// by the current move and sets it accordingly.
-pub fn match_move(
-    attacker: &mut types::Player,
-    opponent: &mut types::Player,
-    idx: usize
-) -> types::SubField {
-
-    let row = idx / 10;
-    let col = idx % 10;
-
-    match opponent.own_board[row][col] {
-        types::SubField::Water => {
-            println!("Miss - try again.");
-            attacker.op_board[row][col] = types::SubField::Miss;
-            opponent.own_board[row][col] = types::SubField::Miss;
-            return types::SubField::Miss
-        },
-        types::SubField::Ship => {
-            println!("Hit!");
-            attacker.op_board[row][col] = types::SubField::Hit;
-            opponent.own_board[row][col] = types::SubField::Hit;
-            opponent.capacity -= 1;
-            return types::SubField::Hit
-        },
-        types::SubField::Hit => {
-            println!("Already hit.");
-            return types::SubField::Hit
-        },
-        types::SubField::Miss => {
-            println!("Miss - try again (already tried).");
-            return types::SubField::Miss
-        }
-    }
-}
-
-/// Calculates smart moves for the ai.
-fn smart_move(mut attacker: &mut types::Player, mut opponent: &mut types::Player) {
-
-    // Holds the remaining indices to aim on.
-    let mut vec = Vec::new();
-    for i in 0..100 {
-        vec.push(i);
-    }
-
-    let mut target = 100;
-
-    // Checks the surrounding of a hit.
-    for row in 0..10 {
-        for col in 0..10 {
-            if attacker.op_board[row][col] == types::SubField::Hit {
-                // The special cases:
-                if row == 0 && col == 0 {
-                    if attacker.op_board[row][col + 1] == types::SubField::Water {
-                        target = 1;
-                    } else {
-                        if attacker.op_board[row + 1][col] == types::SubField::Water {
-                            target = 10;
-                        }
-                    }
-                } else if row == 0 {
-                    if col == 9 {
-                        if attacker.op_board[row][col - 1] == types::SubField::Water {
-                            target = col - 1;
-                        } else {
-                            if attacker.op_board[row + 1][col] == types::SubField::Water {
-                                target = 10 + col;
-                            }
-                        }
-                    } else if attacker.op_board[row][col + 1] == types::SubField::Water {
-                        target = col + 1;
-                    } else if attacker.op_board[row][col - 1] == types::SubField::Water {
-                        target = col - 1;
-                    } else {
-                        if attacker.op_board[row + 1][col] == types::SubField::Water {
-                            target = 10 + col;
-                        }
-                    }
-                } else if col == 0 {
-                    if row == 9 {
-                        if attacker.op_board[row - 1][col] == types::SubField::Water {
-                           target = (row - 1) * 10;
-                        } else {
-                            if attacker.op_board[row][col + 1] == types::SubField::Water {
-                                target = (row * 10) + 1;
-                            }
-                        }
-                    } else if attacker.op_board[row][col + 1] == types::SubField::Water {
-                        target = row * 10 + 1;
-                    } else if attacker.op_board[row - 1][col] == types::SubField::Water {
-                        target = (row - 1) * 10;
-                    } else {
-                        if attacker.op_board[row + 1][col] == types::SubField::Water {
-                            target = (row + 1) * 10;
-                        }
-                    }
-                } else if row == 9 && col == 9 {
-                    if attacker.op_board[row][col - 1] == types::SubField::Water {
-                        target = row * 10 + col - 1;
-                    } else {
-                        if attacker.op_board[row - 1][col] == types::SubField::Water {
-                            target = (row - 1) * 10 + col;
-                        }
-                    }
-                } else if row == 9 {
-                    if attacker.op_board[row][col - 1] == types::SubField::Water {
-                        target = row * 10 + col - 1;
-                    } else if attacker.op_board[row][col + 1] == types::SubField::Water {
-                        target = row * 10 + col + 1;
-                    } else {
-                        if attacker.op_board[row - 1][col] == types::SubField::Water {
-                            target = (row - 1) * 10 + col;
-                        }
-                    }
-                } else if col == 9 {
-                    if attacker.op_board[row][col - 1] == types::SubField::Water {
-                        target = row * 10 + col - 1;
-                    } else if attacker.op_board[row + 1][col] == types::SubField::Water {
-                        target = (row + 1) * 10 + col;
-                    } else {
-                        if attacker.op_board[row - 1][col] == types::SubField::Water {
-                            target = (row - 1) * 10 + col;
-                        }
-                    }
-                // The ordinary cases.
-                } else {
-                    if attacker.op_board[row][col - 1] == types::SubField::Water {
-                        target = row * 10 + col - 1;
-                    } else if attacker.op_board[row][col + 1] == types::SubField::Water {
-                        target = row * 10 + col + 1;
-                    } else if attacker.op_board[row - 1][col] == types::SubField::Water {
-                        target = (row - 1) * 10 + col;
-                    } else {
-                        if attacker.op_board[row + 1][col] == types::SubField::Water {
-                            target = (row + 1) * 10 + col;
-                        }
-                    }
+/// Lets the player whose turn it is perform a move.
+fn make_move(engine: &mut engine::GameEngine, ai: &mut dyn ai::Strategy, rng: &mut StdRng) {
+    let id = engine.current_turn();
+    let idx = if engine.player(id).player_type == types::PlayerType::Human {
+        println!("Enter coordinates, {}:", engine.player(id).name);
+        loop {
+            let input = ::util::read_string();
+            let mut words = input.split_whitespace();
+            if words.next() == Some("save") {
+                let f = words.next().unwrap_or(save::SAVE_FILE);
+                match save_game(engine, ai, rng, f) {
+                    Ok(()) => Green.with(|| println!(
+                        "Game saved, resume it with 'single --resume {}'.", f
+                    )),
+                    Err(e) => Red.with(|| println!("{}", e)),
                 }
+                println!("Enter coordinates, {}:", engine.player(id).name);
+                continue;
             }
-            if target != 100 {
-                break;
+            if let Some(i) = engine.player(id).op_board.get_index(&input) {
+                break i;
             }
         }
-        if target != 100 {
-            break;
+    // AI
+    } else {
+        match ai.target(&engine.player(id).op_board, rng) {
+            Ok(idx) => idx,
+            Err(e) => {
+                Red.with(|| println!("{} forfeits the game: {}", engine.player(id).name, e));
+                engine.resign(id);
+                return;
+            },
         }
-    }
-
-    let mut rng = thread_rng();
+    };
 
-    if target == 100 {
-        target = *rng.choose(&vec).unwrap();
+    repeated_shot(&engine.player(id).op_board, idx);
+    let outcome = match engine.fire(idx) {
+        Ok(outcome) => outcome,
+        // the game can't go on, e.g. a bot aimed beyond the board
+        Err(e) => {
+            Red.with(|| println!(
+                "{} forfeits the game, the shot failed: {:?}", engine.player(id).name, e
+            ));
+            engine.resign(id);
+            return;
+        },
+    };
+    announce(&outcome);
+    if engine.player(id).player_type != types::PlayerType::Human {
+        ai.record(&engine.player(id).op_board, idx, &outcome);
     }
 
-    // Calculates fields as long as an unused one occurs.
-    loop {
-        let row = target / 10;
-        let col = target % 10;
-        // It shouldn't hit a target twice.
-        if attacker.op_board[row][col] == types::SubField::Water { break; }
-        remove_idx(target, &mut vec);
-        target = *rng.choose(&vec).unwrap();
-    }
-    match_move(&mut attacker, &mut opponent, target);
-}
-
-/// Lets the players perform their moves.
-fn make_move(mut attacker: &mut types::Player, mut opponent: &mut types::Player) {
-    if attacker.player_type == types::PlayerType::Human {
-        println!("Enter coordinates, {}:", attacker.name);
-        let mut input;
-        loop {
-            input = ::util::read_string();
-            if valid_coordinate(&input) {
-                break;
-            }
+    if let types::ShotOutcome::Sunk(name) = outcome {
+        let attacker = engine.player(id);
+        if attacker.player_type == types::PlayerType::Human {
+            Green.with(|| println!("You sank the {}!", name));
+        } else {
+            Red.with(|| println!("{} sank your {}!", attacker.name, name));
         }
-        match_move(&mut attacker, &mut opponent, ::Board::get_index(&input));
-    // AI
-    } else {
-        smart_move(&mut attacker, &mut opponent);
     }
 }
 
-/// Returns whether all the player's ships got destroyed.
-pub fn game_over(player: &types::Player) -> bool {
-    player.capacity <= 0
+/// Saves the game to the given file. The random number generator is reseeded
+/// with the saved seed, so that the game goes on alike whether it is resumed or not.
+pub fn save_game(
+    engine: &engine::GameEngine,
+    ai: &dyn ai::Strategy,
+    rng: &mut StdRng,
+    f: &str
+) -> Result<(), String> {
+    let ai = ai.as_ai().ok_or("Games against a bot can't be saved.")?;
+    let seed = rng.gen();
+    *rng = game_rng(Some(seed)).0;
+    save::save(f, engine, ai, seed)
 }
 
-/// Initializes the players and the boards and provides the
-/// game loop which lets the players perform their moves alternately.
-pub fn start_round(name: String, board: [[types::SubField; 10]; 10]) {
-
-    // Creates the initial (empty) boards (10 x 10) for player1.
-    let mut player1 = types::Player {
-        own_board: [[types::SubField::Water; 10]; 10],
-        op_board: [[types::SubField::Water; 10]; 10],
+/// Creates a round of the single-player mode: the player with the given board,
+/// which is still empty if the ships are to be placed, against the strategy.
+pub fn single_round(
+    name: String,
+    board: types::Board,
+    fleet: Vec<types::ShipType>,
+    ai: &dyn ai::Strategy
+) -> engine::GameEngine {
+
+    let dim = board.dim();
+
+    // Creates the initial (empty) boards (N x N) for player1.
+    let player1 = types::Player {
+        own_board: types::Board::init(dim),
+        op_board: types::Board::init(dim),
         capacity: 0,
         // Could be extended later to have an AI vs. AI version.
         player_type: types::PlayerType::Human,
         name: name,
     };
 
-    player1.set_board(board);
-
-    // Creates the initial (empty) boards (10 x 10) for player2.
-    let mut player2 = types::Player {
-        own_board: [[types::SubField::Water; 10]; 10],
-        op_board: [[types::SubField::Water; 10]; 10],
+    // Creates the initial (empty) boards (N x N) for player2.
+    let player2 = types::Player {
+        own_board: types::Board::init(dim),
+        op_board: types::Board::init(dim),
         capacity: 0,
-        player_type : types::PlayerType::AI,
-        name: "AI".to_string(),
+        player_type: ai.player_type(),
+        name: if ai.player_type() == types::PlayerType::Bot { "Bot" } else { "AI" }.to_string(),
     };
 
+    let mut engine = engine::GameEngine::new(player1, player2, fleet);
+    if !board.empty() {
+        if let Err(e) = engine.set_board(types::PlayerId::One, &board) {
+            Red.with(|| println!("Invalid board configuration: {}", e));
+        }
+    }
+    engine
+}
+
+/// Lets the strategy place the ships of the second player.
+/// A strategy that fails forfeits the game.
+pub fn place_opponent(
+    engine: &mut engine::GameEngine,
+    ai: &mut dyn ai::Strategy,
+    rng: &mut StdRng
+) -> Result<(), String> {
+    let dim = engine.player(types::PlayerId::Two).own_board.dim();
+    let fleet = engine.fleet().to_vec();
+    // Don't trust the strategy to follow the placement rules.
+    let placed = ai.place(dim, &fleet, rng).and_then(|board| {
+        engine.set_board(types::PlayerId::Two, &board).map_err(|e| e.to_string())
+    });
+    if placed.is_err() {
+        engine.resign(types::PlayerId::Two);
+    }
+    placed
+}
+
+/// Initializes the players and the boards and starts the game.
+pub fn start_round(
+    name: String,
+    board: types::Board,
+    fleet: Vec<types::ShipType>,
+    mut ai: Box<dyn ai::Strategy>,
+    rng: &mut StdRng
+) {
+    let mut engine = single_round(name, board, fleet.clone(), &*ai);
+
     // Initializes the boards with the player's ships.
     loop {
-        if player1.capacity > 0 {
+        if engine.player(types::PlayerId::One).capacity > 0 {
             break;
         }
-        match place_ships(&mut player1) {
+        match place_ships(&mut engine, types::PlayerId::One) {
             Ok(_) => { break; },
             Err(types::ErrorType::DeadEndHuman) => {
                 println!("No suitable position left, please restart the ship placement.");
-                restart_placement(&mut player1);
+                engine.restart_placement(types::PlayerId::One);
             },
             Err(_) => {},
         }
     }
 
-    loop {
-        match place_ships(&mut player2) {
-            Ok(_) => { break; },
-            Err(types::ErrorType::DeadEndAI) => {
-                restart_placement(&mut player2);
-            },
-            Err(_) => {},
+    if let Err(e) = place_opponent(&mut engine, &mut *ai, rng) {
+        let name = &engine.player(types::PlayerId::Two).name;
+        Red.with(|| println!("{} forfeits the game: {}", name, e));
+    }
+
+    play_round(engine, ai, rng);
+}
+
+/// The game loop which lets the players perform their moves alternately,
+/// either for a new game or a resumed one.
+pub fn play_round(
+    mut engine: engine::GameEngine,
+    mut ai: Box<dyn ai::Strategy>,
+    rng: &mut StdRng
+) {
+    if ai.as_ai().is_some() {
+        Cyan.with(|| println!("Enter 'save [file]' instead of coordinates to save the game."));
+    }
+    while engine.winner().is_none() {
+        if engine.current_turn() == types::PlayerId::One {
+            print_boards(engine.player(types::PlayerId::One));
+        } else {
+            println!("{} - Move:", engine.player(types::PlayerId::Two).name);
         }
+        make_move(&mut engine, &mut *ai, rng);
     }
+    finish_round(&engine, &mut *ai);
+}
 
-    loop {
-        print_boards(&player1);
-        make_move(&mut player1, &mut player2);
-        if game_over(&player2) {
-            println!("G A M E   O V E R");
-            println!("Congratulations, {}", Yellow.paint(player1.name));
-            break;
+/// Announces the winner and records the finished game
+pub fn finish_round(engine: &engine::GameEngine, ai: &mut dyn ai::Strategy) {
+    ai.game_over(engine.winner() == Some(types::PlayerId::Two));
+
+    println!("G A M E   O V E R");
+    match engine.winner() {
+        Some(types::PlayerId::One) => println!(
+            "Congratulations, {}", Yellow.paint(&engine.player(types::PlayerId::One).name)
+        ),
+        _ => println!("Congratulations, Player2"),
+    }
+    replay::Replay::new(&engine).record();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bincode::SizeLimit;
+    use bincode::serde::{deserialize_from, serialize};
+    use model::types::{Board, PlacementError, Ship, ShipType, SubField};
+
+    fn ship(name: &str, size: usize, amount: usize) -> ShipType {
+        ShipType { name: name.to_string(), size: size, amount: amount }
+    }
+
+    /// A Boat of size 2 and a Ship of size 3
+    fn fleet() -> Vec<ShipType> {
+        vec![ship("Boat", 2, 1), ship("Ship", 3, 1)]
+    }
+
+    /// A 6x6 board with ships on the given fields
+    fn board(ships: &[usize]) -> Board {
+        let mut board = Board::init(6);
+        for &idx in ships {
+            board.set(idx, SubField::Ship);
         }
+        board
+    }
 
-        println!("AI - Move:");
+    #[test]
+    fn valid_board() {
+        // the Boat in the top left corner, the Ship upright in the bottom left corner
+        let board = validate_board(&board(&[0, 1, 18, 24, 30]), 6, &fleet()).unwrap();
+        assert_eq!(board.ships().len(), 2);
+        assert_eq!(board.ships()[0].ship_type.name, "Boat");
+        assert_eq!(board.ships()[1].fields, vec![30, 24, 18]);
+    }
 
-        make_move(&mut player2, &mut player1);
-        if game_over(&player1) {
-            println!("G A M E   O V E R");
-            println!("Congratulations, Player2");
-            break;
+    #[test]
+    fn board_of_another_dimension() {
+        assert_eq!(
+            validate_board(&Board::init(5), 6, &fleet()),
+            Err(PlacementError::Dimension(5, 6))
+        );
+    }
+
+    #[test]
+    fn board_with_too_few_fields() {
+        // boards of clients arrive serialized, the amount of fields isn't guaranteed
+        #[derive(Serialize)]
+        struct Raw {
+            dim: usize,
+            fields: Vec<SubField>,
+            ships: Vec<Ship>,
         }
+        let raw = Raw { dim: 6, fields: vec![SubField::Water; 30], ships: Vec::new() };
+        let bytes = serialize(&raw, SizeLimit::Infinite).unwrap();
+        let board: Board = deserialize_from(&mut &bytes[..], SizeLimit::Infinite).unwrap();
+        assert_eq!(validate_board(&board, 6, &fleet()), Err(PlacementError::Dimension(6, 6)));
+    }
+
+    #[test]
+    fn crooked_ship() {
+        assert_eq!(
+            validate_board(&board(&[0, 1, 7, 30, 31]), 6, &fleet()),
+            Err(PlacementError::Shape(0))
+        );
+    }
+
+    #[test]
+    fn ship_of_unknown_size() {
+        assert_eq!(
+            validate_board(&board(&[0, 3, 4, 24, 30]), 6, &fleet()),
+            Err(PlacementError::UnknownSize(0, 1))
+        );
+    }
+
+    #[test]
+    fn one_ship_too_many() {
+        assert_eq!(
+            validate_board(&board(&[0, 1, 4, 5, 24, 25, 26]), 6, &fleet()),
+            Err(PlacementError::TooMany(4, "Boat".to_string()))
+        );
+    }
+
+    #[test]
+    fn ships_touching_diagonally() {
+        assert_eq!(
+            validate_board(&board(&[0, 1, 8, 9, 10]), 6, &fleet()),
+            Err(PlacementError::Collision(8, "Ship".to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_ship() {
+        assert_eq!(
+            validate_board(&board(&[0, 1]), 6, &fleet()),
+            Err(PlacementError::Missing("Ship".to_string(), 1))
+        );
+    }
+
+    #[test]
+    fn valid_fleets() {
+        assert_eq!(valid_fleet(&default_fleet(10), 10), Ok(()));
+        assert_eq!(valid_fleet(&[ship("Boat", 2, 1)], 6), Ok(()));
+    }
+
+    #[test]
+    fn invalid_fleets() {
+        assert!(valid_fleet(&[], 10).is_err());
+        assert!(valid_fleet(&[ship("Boat", 0, 1)], 10).is_err());
+        assert!(valid_fleet(&[ship("Boat", 11, 1)], 10).is_err());
+        assert!(valid_fleet(&[ship("Boat", 2, 30)], 10).is_err());
+        // no capacity at all
+        assert!(valid_fleet(&[ship("Boat", 2, 0)], 10).is_err());
+        assert!(valid_fleet(&[ship("Boat", 2, 1), ship("Raft", 3, 0)], 10).is_err());
+        assert!(valid_fleet(&[ship("Boat", 2, 1), ship("Boat", 3, 1)], 10).is_err());
     }
 }
@@ -0,0 +1,242 @@
+// Replays: every game is recorded to a file when it ends, so that it can be
+// stepped through afterwards with the `replay` subcommand.
+use bincode;
+use bincode::serde::{deserialize_from, serialize_into};
+use model::engine::GameEngine;
+use model::print;
+use model::types::{Board, PlayerId, ShipType, Shot, ShotOutcome, SubField};
+use std::fs::{File, OpenOptions};
+use std::io::{stdin, BufReader, BufWriter, ErrorKind};
+use std::time::{SystemTime, UNIX_EPOCH};
+use term_painter::ToStyle;
+use term_painter::Color::*;
+
+// the size limit of a replay file, a game on the largest board takes far less
+const MAX_SIZE: u64 = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Replay {
+    pub dim: usize,
+    pub fleet: Vec<ShipType>,
+    pub names: [String; 2],
+    // the boards as placed; None if the board stayed unknown
+    pub placements: [Option<Board>; 2],
+    pub shots: Vec<Shot>,
+    pub winner: Option<PlayerId>,
+}
+
+impl Replay {
+    /// Records the given game
+    pub fn new(engine: &GameEngine) -> Replay {
+        let one = engine.player(PlayerId::One);
+        let two = engine.player(PlayerId::Two);
+        Replay {
+            dim: one.own_board.dim(),
+            fleet: engine.fleet().to_vec(),
+            names: [one.name.clone(), two.name.clone()],
+            placements: [
+                engine.placement(PlayerId::One).cloned(),
+                engine.placement(PlayerId::Two).cloned(),
+            ],
+            shots: engine.shots().to_vec(),
+            winner: engine.winner(),
+        }
+    }
+
+    /// Adds a board that was revealed after the game
+    pub fn reveal(&mut self, id: PlayerId, board: Board) {
+        self.placements[id.index()] = Some(board);
+    }
+
+    /// Writes the replay to a new file in the current directory and returns its name
+    pub fn save(&self) -> Result<String, String> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // another game may have finished in the same second in this directory, e.g.
+        // the other side of a network game, so existing files get a number appended
+        let mut attempt = 0;
+        let (name, file) = loop {
+            let name = match attempt {
+                0 => format!("battleship-{}.replay", time),
+                n => format!("battleship-{}-{}.replay", time, n),
+            };
+            match OpenOptions::new().write(true).create_new(true).open(&name) {
+                Ok(file) => break (name, file),
+                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => attempt += 1,
+                Err(e) => return Err(format!("Could not create '{}': {}", name, e)),
+            }
+        };
+        serialize_into(&mut BufWriter::new(file), self, bincode::SizeLimit::Infinite)
+            .map_err(|e| format!("Could not write '{}': {}", name, e))?;
+        Ok(name)
+    }
+
+    /// Saves the replay and tells the player where to find it
+    pub fn record(&self) {
+        match self.save() {
+            Ok(name) => println!("The game was recorded to '{}'.", name),
+            Err(e) => Red.with(|| println!("{}", e)),
+        }
+    }
+
+    /// Reads a replay file and checks that the game fits on its board
+    pub fn load(f: &str) -> Result<Replay, String> {
+        let file = File::open(f).map_err(|e| format!("Could not open '{}': {}", f, e))?;
+        let replay: Replay = deserialize_from(
+            &mut BufReader::new(file),
+            bincode::SizeLimit::Bounded(MAX_SIZE)
+        ).map_err(|_| format!("'{}' is not a replay file", f))?;
+        replay.validate().map_err(|e| format!("'{}' is damaged: {}", f, e))?;
+        Ok(replay)
+    }
+
+    /// Checks the dimension of the board, the placements and every shot
+    fn validate(&self) -> Result<(), String> {
+        if self.dim < ::MIN_BOARD_SIZE || self.dim > ::MAX_BOARD_SIZE {
+            return Err(format!("there are no {0}x{0} boards", self.dim));
+        }
+        for board in self.placements.iter().filter_map(|board| board.as_ref()) {
+            if board.dim() != self.dim || board.size() != self.dim * self.dim {
+                return Err(format!("a board doesn't have {0}x{0} fields", self.dim));
+            }
+        }
+        if let Some(shot) = self.shots.iter().find(|shot| shot.field >= self.dim * self.dim) {
+            return Err(format!("the shot at field {} misses the board", shot.field));
+        }
+        Ok(())
+    }
+
+    /// Returns the boards of both players after the given number of shots
+    fn boards(&self, shots: usize) -> [Board; 2] {
+        let mut boards = [
+            self.placements[0].clone().unwrap_or(Board::init(self.dim)),
+            self.placements[1].clone().unwrap_or(Board::init(self.dim)),
+        ];
+        for shot in &self.shots[..shots] {
+            let board = &mut boards[shot.player.other().index()];
+            if shot.outcome == ShotOutcome::Miss {
+                board.set(shot.field, SubField::Miss);
+            } else {
+                board.set(shot.field, SubField::Hit);
+            }
+        }
+        boards
+    }
+
+    /// Steps through the game, forward and back
+    pub fn show(&self) {
+        let mut step = 0;
+        loop {
+            let boards = self.boards(step);
+            println!("\n{} (left) vs. {} (right)", self.names[0], self.names[1]);
+            print(&boards[0], &boards[1]);
+
+            if step == 0 {
+                println!("Placement of the ships, {} shots to come.", self.shots.len());
+            } else {
+                let shot = &self.shots[step - 1];
+                println!(
+                    "Shot {}/{} at {}: {} fires at {} - {}",
+                    step,
+                    self.shots.len(),
+                    clock(shot.time),
+                    self.names[shot.player.index()],
+                    boards[0].coordinate(shot.field),
+                    shot.outcome,
+                );
+            }
+            if step == self.shots.len() {
+                match self.winner {
+                    Some(id) => println!("{} won the game.", self.names[id.index()]),
+                    None => println!("The game wasn't finished."),
+                }
+            }
+
+            println!("[Enter] next, [b] back, [s] start, [e] end, [q] quit");
+            let mut input = String::new();
+            match stdin().read_line(&mut input) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {},
+            }
+            step = match input.trim() {
+                "" | "n" => (step + 1).min(self.shots.len()),
+                "b" => step.saturating_sub(1),
+                "s" => 0,
+                "e" => self.shots.len(),
+                "q" => return,
+                _ => step,
+            };
+        }
+    }
+}
+
+/// Formats the given time as hh:mm:ss (UTC)
+fn clock(time: u64) -> String {
+    format!("{:02}:{:02}:{:02}", time / 3600 % 24, time / 60 % 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn replay() -> Replay {
+        Replay {
+            dim: 6,
+            fleet: vec![ShipType { name: "Boat".to_string(), size: 2, amount: 1 }],
+            names: ["One".to_string(), "Two".to_string()],
+            placements: [Some(Board::init(6)), None],
+            shots: vec![
+                Shot { player: PlayerId::One, field: 35, outcome: ShotOutcome::Miss, time: 0 },
+            ],
+            winner: None,
+        }
+    }
+
+    /// Writes the given bytes to a file and loads it as a replay
+    fn load(name: &str, bytes: &[u8]) -> Result<Replay, String> {
+        let path = env::temp_dir().join(format!("battleship-{}-{}.replay", name, process::id()));
+        fs::write(&path, bytes).unwrap();
+        let replay = Replay::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        replay
+    }
+
+    fn bytes(replay: &Replay) -> Vec<u8> {
+        bincode::serde::serialize(replay, bincode::SizeLimit::Infinite).unwrap()
+    }
+
+    #[test]
+    fn valid_replay() {
+        let replay = load("valid", &bytes(&replay())).unwrap();
+        assert_eq!(replay.boards(1)[1].get(35), SubField::Miss);
+    }
+
+    #[test]
+    fn damaged_replays() {
+        let mut shot = replay();
+        shot.shots[0].field = 36;
+        assert!(load("shot", &bytes(&shot)).is_err());
+
+        let mut placement = replay();
+        placement.placements[1] = Some(Board::init(7));
+        assert!(load("placement", &bytes(&placement)).is_err());
+
+        let mut dim = replay();
+        dim.dim = 1 << 20;
+        dim.placements = [None, None];
+        dim.shots.clear();
+        assert!(load("dim", &bytes(&dim)).is_err());
+    }
+
+    #[test]
+    fn oversized_replays_are_rejected() {
+        let mut huge = replay();
+        huge.names[0] = "x".repeat(MAX_SIZE as usize);
+        assert!(load("huge", &bytes(&huge)).is_err());
+    }
+}
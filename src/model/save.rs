@@ -0,0 +1,36 @@
+// Saved games: a single-player game can be saved at any coordinate prompt
+// and resumed later with `single --resume <file>`. The file holds the engine
+// (both players, the turn and the shots so far), the state of the AI and the
+// seed the random number generator continues with.
+use bincode;
+use bincode::serde::{deserialize_from, serialize_into};
+use model::ai::Ai;
+use model::engine::GameEngine;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// The file a game is saved to unless another one is given
+pub const SAVE_FILE: &'static str = "battleship.save";
+
+/// Writes the game to the given file
+pub fn save(f: &str, engine: &GameEngine, ai: &Ai, seed: u64) -> Result<(), String> {
+    let file = File::create(f).map_err(|e| format!("Could not create '{}': {}", f, e))?;
+    let mut writer = BufWriter::new(file);
+    let limit = bincode::SizeLimit::Infinite;
+    serialize_into(&mut writer, engine, limit)
+        .and_then(|_| serialize_into(&mut writer, ai, limit))
+        .and_then(|_| serialize_into(&mut writer, &seed, limit))
+        .map_err(|e| format!("Could not write '{}': {}", f, e))
+}
+
+/// Reads a game written by `save()`
+pub fn load(f: &str) -> Result<(GameEngine, Ai, u64), String> {
+    let file = File::open(f).map_err(|e| format!("Could not open '{}': {}", f, e))?;
+    let mut reader = BufReader::new(file);
+    let limit = bincode::SizeLimit::Infinite;
+    let invalid = |_| format!("'{}' is not a saved game", f);
+    let engine = deserialize_from(&mut reader, limit).map_err(&invalid)?;
+    let ai = deserialize_from(&mut reader, limit).map_err(&invalid)?;
+    let seed = deserialize_from(&mut reader, limit).map_err(&invalid)?;
+    Ok((engine, ai, seed))
+}
@@ -1,9 +1,13 @@
 use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
 
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub enum PlayerType {
     Human,
     AI,
+    // an external program, see bot
+    Bot,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -14,22 +18,219 @@ pub enum SubField {
     Miss,
 }
 
+#[derive(PartialEq, Debug)]
 pub enum ErrorType {
     DeadEndHuman,
     DeadEndAI,
     InvalidField,
+    NotReady,
+    GameOver,
 }
 
+/// The two players of a round
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PlayerId {
+    One,
+    Two,
+}
+
+impl PlayerId {
+    /// Returns the opponent
+    pub fn other(self) -> PlayerId {
+        match self {
+            PlayerId::One => PlayerId::Two,
+            PlayerId::Two => PlayerId::One,
+        }
+    }
+
+    /// Position of the player within a round
+    pub fn index(self) -> usize {
+        match self {
+            PlayerId::One => 0,
+            PlayerId::Two => 1,
+        }
+    }
+}
+
+/// Violations of the placement rules found on a complete board.
+/// The field given is the first field of the ship in question.
+#[derive(Debug, PartialEq)]
+pub enum PlacementError {
+    // found and expected dimension
+    Dimension(usize, usize),
+    Shape(usize),
+    UnknownSize(usize, usize),
+    TooMany(usize, String),
+    Collision(usize, String),
+    // name of the ship and amount of missing ships
+    Missing(String, usize),
+}
+
+impl PlacementError {
+    /// Returns the field the error refers to, if any
+    pub fn field(&self) -> Option<usize> {
+        match *self {
+            PlacementError::Shape(idx)
+            | PlacementError::UnknownSize(idx, _)
+            | PlacementError::TooMany(idx, _)
+            | PlacementError::Collision(idx, _) => Some(idx),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlacementError::Dimension(found, expected) => write!(
+                f, "the board has {0}x{0} fields instead of {1}x{1}", found, expected
+            ),
+            PlacementError::Shape(_) => write!(f, "the ship is not a straight line"),
+            PlacementError::UnknownSize(_, size) => write!(
+                f, "the fleet contains no ship of size {}", size
+            ),
+            PlacementError::TooMany(_, ref name) => write!(f, "one {} too many", name),
+            PlacementError::Collision(_, ref name) => write!(
+                f, "the {} touches another ship", name
+            ),
+            PlacementError::Missing(ref name, amount) => write!(
+                f, "{} {} missing", amount, name
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ShipType {
     pub name: String,
     pub size: usize,
     pub amount: usize,
 }
 
+/// A single ship on the board: its type and the fields it occupies.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Ship {
+    pub ship_type: ShipType,
+    pub fields: Vec<usize>,
+}
+
+/// The outcome of a shot at the opponent's board.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ShotOutcome {
+    Miss,
+    Hit,
+    // Name of the ship that went down.
+    Sunk(String),
+}
+
+impl fmt::Display for ShotOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShotOutcome::Miss => write!(f, "miss"),
+            ShotOutcome::Hit => write!(f, "hit"),
+            ShotOutcome::Sunk(ref name) => write!(f, "sunk {}", name),
+        }
+    }
+}
+
+/// The coordinate of a field as the players see it: the column is labeled with
+/// letters starting at 'A' on the left ("AA" follows "Z"), the rows are numbered
+/// starting at 0 at the bottom of the board. Both start at 0 here.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Coord {
+    pub col: usize,
+    pub row: usize,
+}
+
+impl FromStr for Coord {
+    type Err = String;
+
+    /// Parses coordinates like "B4", "b4", "4B", "B12", "b-4" or "B 4", or the
+    /// column and the row as numbers like "(1,4)"
+    fn from_str(s: &str) -> Result<Coord, String> {
+        let invalid = || format!("invalid coordinate '{}'", s.trim());
+
+        // runs of letters and of digits, split by separators
+        let mut parts: Vec<String> = Vec::new();
+        let mut last = None;
+        for c in s.trim().chars() {
+            let kind = if c.is_ascii_alphabetic() {
+                Some(true)
+            } else if c.is_ascii_digit() {
+                Some(false)
+            } else if c.is_whitespace() || "-,()".contains(c) {
+                None
+            } else {
+                return Err(invalid());
+            };
+            match kind {
+                Some(alpha) if last == Some(alpha) => parts.last_mut().unwrap().push(c),
+                Some(_) => parts.push(c.to_string()),
+                None => {},
+            }
+            last = kind;
+        }
+
+        let number = |part: &str| part.parse::<usize>().map_err(|_| invalid());
+        let letters = |part: &str| -> Result<usize, String> {
+            // bijective base 26: A = 0, Z = 25, AA = 26
+            let mut col = 0usize;
+            for c in part.to_ascii_uppercase().bytes() {
+                col = col.checked_mul(26)
+                    .and_then(|col| col.checked_add((c - b'A') as usize + 1))
+                    .ok_or_else(invalid)?;
+            }
+            Ok(col - 1)
+        };
+
+        let alpha = |part: &String| part.as_bytes()[0].is_ascii_alphabetic();
+        let (col, row) = match parts.as_slice() {
+            [ref a, ref b] => match (alpha(a), alpha(b)) {
+                (true, false) => (letters(a)?, number(b)?),
+                (false, true) => (letters(b)?, number(a)?),
+                (false, false) => (number(a)?, number(b)?),
+                (true, true) => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(Coord { col: col, row: row })
+    }
+}
+
+impl Coord {
+    /// The label of the given column, e.g. 0 => "A", 25 => "Z", 26 => "AA"
+    pub fn column(col: usize) -> String {
+        let mut letters = Vec::new();
+        let mut col = col + 1;
+        while col > 0 {
+            letters.push(b'A' + ((col - 1) % 26) as u8);
+            col = (col - 1) / 26;
+        }
+        letters.reverse();
+        String::from_utf8(letters).unwrap()
+    }
+}
+
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", Coord::column(self.col), self.row)
+    }
+}
+
+/// A single shot of a game
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Shot {
+    pub player: PlayerId,
+    pub field: usize,
+    pub outcome: ShotOutcome,
+    // seconds since the UNIX epoch
+    pub time: u64,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Player {
-    pub own_board: [[SubField; 10]; 10],
-    pub op_board: [[SubField; 10]; 10],
+    pub own_board: Board,
+    pub op_board: Board,
     pub player_type: PlayerType,
     // The "life" basically - the amount of hits necessary
     // for the opponent to win the game.
@@ -37,78 +238,263 @@ pub struct Player {
     pub name: String,
 }
 
-impl Player {
-    pub fn set_board(&mut self, b: [[SubField; 10]; 10]) {
-        self.own_board = b.clone();
-        self.capacity = Board::targets(&b);
-    }
-}
-
 impl fmt::Display for SubField {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            SubField::Water => write!(f, " ~ "),
-            SubField::Ship => write!(f, " ⛵ "),
-            SubField::Hit => write!(f, " ❌ "),
-            SubField::Miss => write!(f, " ○ ")
+            SubField::Water => write!(f, "~"),
+            SubField::Ship => write!(f, "#"),
+            SubField::Hit => write!(f, "X"),
+            SubField::Miss => write!(f, "o")
         }
     }
 }
 
-pub struct Board;
+/// A quadratic board of `dim` x `dim` fields, stored row by row.
+/// Row 0 is the top row of the board.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Board {
+    dim: usize,
+    fields: Vec<SubField>,
+    ships: Vec<Ship>,
+}
 
 impl Board {
-    /// Initializes a board with just water
-    pub fn init() -> [[SubField; 10]; 10] {
-        [[SubField::Water; 10]; 10]
-    }
-
-    /// Returns index on board for given inputs
-    pub fn get_index(coord: &str) -> usize {
-        let case = coord.to_uppercase();
-        match case.as_ref() {
-            "A0"|"0A" => 90, "A1"|"1A" => 80, "A2"|"2A" => 70, "A3"|"3A" => 60, "A4"|"4A" => 50,
-            "A5"|"5A" => 40, "A6"|"6A" => 30, "A7"|"7A" => 20, "A8"|"8A" => 10, "A9"|"9A" => 0,
-            "B0"|"0B" => 91, "B1"|"1B" => 81, "B2"|"2B" => 71, "B3"|"3B" => 61, "B4"|"4B" => 51,
-            "B5"|"5B" => 41, "B6"|"6B" => 31, "B7"|"7B" => 21, "B8"|"8B" => 11, "B9"|"9B" => 1,
-            "C0"|"0C" => 92, "C1"|"1C" => 82, "C2"|"2C" => 72, "C3"|"3C" => 62, "C4"|"4C" => 52,
-            "C5"|"5C" => 42, "C6"|"6C" => 32, "C7"|"7C" => 22, "C8"|"8C" => 12, "C9"|"9C" => 2,
-            "D0"|"0D" => 93, "D1"|"1D" => 83, "D2"|"2D" => 73, "D3"|"3D" => 63, "D4"|"4D" => 53,
-            "D5"|"5D" => 43, "D6"|"6D" => 33, "D7"|"7D" => 23, "D8"|"8D" => 13, "D9"|"9D" => 3,
-            "E0"|"0E" => 94, "E1"|"1E" => 84, "E2"|"2E" => 74, "E3"|"3E" => 64, "E4"|"4E" => 54,
-            "E5"|"5E" => 44, "E6"|"6E" => 34, "E7"|"7E" => 24, "E8"|"8E" => 14, "E9"|"9E" => 4,
-            "F0"|"0F" => 95, "F1"|"1F" => 85, "F2"|"2F" => 75, "F3"|"3F" => 65, "F4"|"4F" => 55,
-            "F5"|"5F" => 45, "F6"|"6F" => 35, "F7"|"7F" => 25, "F8"|"8F" => 15, "F9"|"9F" => 5,
-            "G0"|"0G" => 96, "G1"|"1G" => 86, "G2"|"2G" => 76, "G3"|"3G" => 66, "G4"|"4G" => 56,
-            "G5"|"5G" => 46, "G6"|"6G" => 36, "G7"|"7G" => 26, "G8"|"8G" => 16, "G9"|"9G" => 6,
-            "H0"|"0H" => 97, "H1"|"1H" => 87, "H2"|"2H" => 77, "H3"|"3H" => 67, "H4"|"4H" => 57,
-            "H5"|"5H" => 47, "H6"|"6H" => 37, "H7"|"7H" => 27, "H8"|"8H" => 17, "H9"|"9H" => 7,
-            "I0"|"0I" => 98, "I1"|"1I" => 88, "I2"|"2I" => 78, "I3"|"3I" => 68, "I4"|"4I" => 58,
-            "I5"|"5I" => 48, "I6"|"6I" => 38, "I7"|"7I" => 28, "I8"|"8I" => 18, "I9"|"9I" => 8,
-            "J0"|"0J" => 99, "J1"|"1J" => 89, "J2"|"2J" => 79, "J3"|"3J" => 69, "J4"|"4J" => 59,
-            "J5"|"5J" => 49, "J6"|"6J" => 39, "J7"|"7J" => 29, "J8"|"8J" => 19, "J9"|"9J" => 9,
-            _ => 100,
+    /// Initializes a board of `dim` x `dim` fields with just water
+    pub fn init(dim: usize) -> Board {
+        Board {
+            dim: dim,
+            fields: vec![SubField::Water; dim * dim],
+            ships: Vec::new(),
         }
     }
 
-    /// Returns true if no Ships set on board
-    pub fn empty(board: &[[SubField; 10]; 10]) -> bool {
-        let mut empty = true;
-        for i in 0..10 {
-            if !empty {
-                return empty
+    /// Returns the dimension N of the N x N board
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Returns the total number of fields on the board
+    pub fn size(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns the field at the given index
+    pub fn get(&self, idx: usize) -> SubField {
+        self.fields[idx]
+    }
+
+    /// Sets the field at the given index
+    pub fn set(&mut self, idx: usize, field: SubField) {
+        self.fields[idx] = field;
+    }
+
+    /// Places a ship of the given type on the given fields
+    pub fn place_ship(&mut self, ship_type: ShipType, fields: Vec<usize>) {
+        for i in fields.iter() {
+            self.fields[*i] = SubField::Ship;
+        }
+        self.ships.push(Ship {
+            ship_type: ship_type,
+            fields: fields,
+        });
+    }
+
+    /// Returns the ships on the board
+    pub fn ships(&self) -> &[Ship] {
+        &self.ships
+    }
+
+    /// Returns the ship occupying the field at the given index
+    pub fn ship_at(&self, idx: usize) -> Option<&Ship> {
+        self.ships.iter().find(|ship| ship.fields.contains(&idx))
+    }
+
+    /// Returns true if every field of the ship at the given index got hit
+    pub fn sunk(&self, idx: usize) -> bool {
+        match self.ship_at(idx) {
+            Some(ship) => ship.fields.iter().all(|&i| self.fields[i] == SubField::Hit),
+            None => false,
+        }
+    }
+
+    /// Fires at the field at the given index and marks it as hit or missed
+    pub fn fire(&mut self, idx: usize) -> ShotOutcome {
+        match self.fields[idx] {
+            SubField::Water | SubField::Miss => {
+                self.fields[idx] = SubField::Miss;
+                ShotOutcome::Miss
+            },
+            SubField::Hit => ShotOutcome::Hit,
+            SubField::Ship => {
+                self.fields[idx] = SubField::Hit;
+                match self.ship_at(idx) {
+                    Some(ship) if self.sunk(idx) => ShotOutcome::Sunk(ship.ship_type.name.clone()),
+                    _ => ShotOutcome::Hit,
+                }
+            },
+        }
+    }
+
+    /// Returns the groups of orthogonally connected ship fields on the board,
+    /// regardless of the ships registered for the board
+    pub fn ship_groups(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.size()];
+        let mut groups = Vec::new();
+
+        for start in 0..self.size() {
+            if visited[start] || self.fields[start] != SubField::Ship {
+                continue;
+            }
+            visited[start] = true;
+            let mut group = Vec::new();
+            let mut stack = vec![start];
+            while let Some(idx) = stack.pop() {
+                group.push(idx);
+                for n in self.neighbours(idx) {
+                    if !visited[n] && self.fields[n] == SubField::Ship {
+                        visited[n] = true;
+                        stack.push(n);
+                    }
+                }
             }
-            empty = board[i].iter().all(|elem| *elem == SubField::Water);
+            group.sort();
+            groups.push(group);
+        }
+        groups
+    }
+
+    /// Returns index on board for given inputs, e.g. "B4", "4b" or "C12",
+    /// see `Coord` for all formats. Returns None if it's not on the board.
+    pub fn get_index(&self, coord: &str) -> Option<usize> {
+        coord.parse().ok().and_then(|coord| self.index_of(coord))
+    }
+
+    /// Returns the index of the field at the given coordinate, if it's on the board.
+    /// Row 0 is the bottom row, while index 0 is in the top row.
+    pub fn index_of(&self, coord: Coord) -> Option<usize> {
+        if coord.col < self.dim && coord.row < self.dim {
+            Some((self.dim - 1 - coord.row) * self.dim + coord.col)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the coordinate of the field at the given index
+    pub fn coord(&self, idx: usize) -> Coord {
+        Coord {
+            col: idx % self.dim,
+            row: self.dim - 1 - idx / self.dim,
         }
-        empty
+    }
+
+    /// Returns the coordinate (e.g. "B4") of the field at the given index
+    pub fn coordinate(&self, idx: usize) -> String {
+        self.coord(idx).to_string()
+    }
+
+    /// Returns the indices of the orthogonal neighbours of the given field
+    pub fn neighbours(&self, idx: usize) -> Vec<usize> {
+        let row = idx / self.dim;
+        let col = idx % self.dim;
+        let mut res = Vec::new();
+        if col > 0 {
+            res.push(idx - 1);
+        }
+        if col + 1 < self.dim {
+            res.push(idx + 1);
+        }
+        if row > 0 {
+            res.push(idx - self.dim);
+        }
+        if row + 1 < self.dim {
+            res.push(idx + self.dim);
+        }
+        res
+    }
+
+    /// Returns true if no Ships set on board
+    pub fn empty(&self) -> bool {
+        self.fields.iter().all(|elem| *elem == SubField::Water)
     }
 
     /// Returns number of Ships on board
-    pub fn targets(board: &[[SubField; 10]; 10]) -> usize {
-        let mut cnt = 0;
-        for i in 0..10 {
-            cnt += board[i].iter().filter(|&elem| *elem == SubField::Ship).count();
+    pub fn targets(&self) -> usize {
+        self.fields.iter().filter(|&elem| *elem == SubField::Ship).count()
+    }
+}
+
+/// Row access, so that a field can be addressed with `board[row][col]`.
+impl Index<usize> for Board {
+    type Output = [SubField];
+
+    fn index(&self, row: usize) -> &[SubField] {
+        &self.fields[row * self.dim..(row + 1) * self.dim]
+    }
+}
+
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, row: usize) -> &mut [SubField] {
+        &mut self.fields[row * self.dim..(row + 1) * self.dim]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(col: usize, row: usize) -> Coord {
+        Coord { col: col, row: row }
+    }
+
+    #[test]
+    fn lenient_coordinates() {
+        for input in &["B4", "b4", "4B", "4b", "b-4", "B 4", " B4 ", "(1,4)", "1, 4"] {
+            assert_eq!(input.parse(), Ok(coord(1, 4)), "{}", input);
         }
-        cnt
+        assert_eq!("C12".parse(), Ok(coord(2, 12)));
+        assert_eq!("A0".parse(), Ok(coord(0, 0)));
+    }
+
+    #[test]
+    fn columns_with_several_letters() {
+        assert_eq!("Z3".parse(), Ok(coord(25, 3)));
+        assert_eq!("AA3".parse(), Ok(coord(26, 3)));
+        assert_eq!("az3".parse(), Ok(coord(51, 3)));
+        assert_eq!("BA3".parse(), Ok(coord(52, 3)));
+        assert_eq!("AAA3".parse(), Ok(coord(702, 3)));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for &(col, row, text) in &[(0, 0, "A0"), (25, 9, "Z9"), (26, 10, "AA10"), (701, 1, "ZZ1")] {
+            assert_eq!(coord(col, row).to_string(), text);
+            assert_eq!(text.parse(), Ok(coord(col, row)));
+        }
+        for col in 0..2000 {
+            assert_eq!(coord(col, 7).to_string().parse(), Ok(coord(col, 7)));
+        }
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        let inputs = [
+            "", "B", "4", "AB", "4 5 6", "B4C", "4B4", "B#4", "B4!", "é4", "B.4",
+            "B99999999999999999999999", "ZZZZZZZZZZZZZZZZZZZZ1",
+        ];
+        for input in &inputs {
+            assert!(input.parse::<Coord>().is_err(), "{}", input);
+        }
+        assert_eq!("B#4".parse::<Coord>(), Err("invalid coordinate 'B#4'".to_string()));
+    }
+
+    #[test]
+    fn indices_of_coordinates() {
+        let board = Board::init(10);
+        // row 0 is at the bottom for the players, but at the top of the board
+        assert_eq!(board.get_index("A9"), Some(0));
+        assert_eq!(board.get_index("J0"), Some(99));
+        assert_eq!(board.get_index("K0"), None);
+        assert_eq!(board.get_index("A10"), None);
+        assert_eq!(board.coordinate(0), "A9");
     }
 }
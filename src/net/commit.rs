@@ -0,0 +1,204 @@
+// Commit-reveal protocol: both players commit to their board at the beginning
+// of the game by exchanging a salted hash. Every player answers the shots at their
+// own board. At the end of the game the boards and salts get revealed and
+// checked against the commitments and the given answers.
+use model;
+use model::types::{Board, ShipType, ShotOutcome, SubField};
+use rand::{OsRng, Rng};
+
+const SALT_LEN: usize = 32;
+
+/// Creates a random salt for a commitment
+pub fn salt() -> Vec<u8> {
+    let mut salt = vec![0; SALT_LEN];
+    OsRng::new()
+        .expect("no source of randomness available")
+        .fill_bytes(&mut salt);
+    salt
+}
+
+/// Returns the commitment for the given board: SHA-256(salt || board)
+/// Only the dimension and the ship fields are part of the commitment.
+pub fn commitment(board: &Board, salt: &[u8]) -> Vec<u8> {
+    let mut data = salt.to_vec();
+    data.extend_from_slice(&(board.dim() as u32).to_be_bytes());
+    for idx in 0..board.size() {
+        data.push(if board.get(idx) == SubField::Ship { 1 } else { 0 });
+    }
+    sha256(&data).to_vec()
+}
+
+/// Checks the revealed board of the opponent: It has to match the commitment,
+/// follow the placement rules and every answer to our shots has to be honest.
+pub fn check_reveal(
+    commitment_recv: &[u8],
+    salt: &[u8],
+    board: &Board,
+    dim: usize,
+    fleet: &[ShipType],
+    answers: &[(usize, ShotOutcome)],
+) -> Result<(), String> {
+    if commitment(board, salt).as_slice() != commitment_recv {
+        return Err("the revealed board doesn't match the commitment".to_string());
+    }
+
+    let mut board = match model::validate_board(board, dim, fleet) {
+        Ok(board) => board,
+        Err(e) => return Err(format!("the revealed board violates the rules: {}", e)),
+    };
+
+    for &(idx, ref answer) in answers {
+        if idx >= board.size() {
+            return Err(format!("an answer refers to the invalid field {}", idx));
+        }
+        let actual = board.fire(idx);
+        if !same_answer(answer, &actual, fleet) {
+            return Err(format!(
+                "the answer to the shot at {} was '{}' instead of '{}'",
+                board.coordinate(idx),
+                answer,
+                actual,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether an answer matches the actual outcome of a shot. Ships of the same size
+/// can't be told apart on a revealed board, so a sunk ship only has to be of the
+/// size of the one named in the answer.
+fn same_answer(answer: &ShotOutcome, actual: &ShotOutcome, fleet: &[ShipType]) -> bool {
+    let size = |name: &str| fleet.iter().find(|ship| ship.name == name).map(|ship| ship.size);
+    match (answer, actual) {
+        (&ShotOutcome::Sunk(ref said), &ShotOutcome::Sunk(ref sunk)) => {
+            size(said).is_some() && size(said) == size(sunk)
+        },
+        _ => answer == actual,
+    }
+}
+
+/// Plain SHA-256 (FIPS 180-4)
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+        0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    // padding: a single 1 bit, zeros and the message length in bits
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                chunk[4 * i], chunk[4 * i + 1], chunk[4 * i + 2], chunk[4 * i + 3]
+            ]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let mut v = h;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7]
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+
+            v[7] = v[6];
+            v[6] = v[5];
+            v[5] = v[4];
+            v[4] = v[3].wrapping_add(t1);
+            v[3] = v[2];
+            v[2] = v[1];
+            v[1] = v[0];
+            v[0] = t1.wrapping_add(t2);
+        }
+
+        for i in 0..8 {
+            h[i] = h[i].wrapping_add(v[i]);
+        }
+    }
+
+    let mut res = [0u8; 32];
+    for i in 0..8 {
+        res[4 * i..4 * i + 4].copy_from_slice(&h[i].to_be_bytes());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn sha256_known_answers() {
+        // test vectors of FIPS 180-4, the last one needs a second block of padding
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn ships_of_the_same_size_are_interchangeable() {
+        let ship = |name: &str| ShipType { name: name.to_string(), size: 3, amount: 1 };
+        let fleet = vec![ship("Cruiser"), ship("Submarine")];
+        // the Submarine comes first on the board, the Cruiser first in the fleet
+        let mut board = Board::init(6);
+        board.place_ship(ship("Submarine"), vec![0, 1, 2]);
+        board.place_ship(ship("Cruiser"), vec![24, 25, 26]);
+
+        let mut answered = board.clone();
+        let answers: Vec<_> = [0, 1, 2, 24, 25, 26].iter()
+            .map(|&idx| (idx, answered.fire(idx)))
+            .collect();
+        let salt = salt();
+        let committed = commitment(&board, &salt);
+        assert_eq!(check_reveal(&committed, &salt, &board, 6, &fleet, &answers), Ok(()));
+
+        let mut lies = answers.clone();
+        lies[2].1 = ShotOutcome::Hit;
+        assert!(check_reveal(&committed, &salt, &board, 6, &fleet, &lies).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Framing: every message is sent as a 4 byte length (big endian) followed by
+// the encoded message. Frames are limited to MAX_LEN bytes, so a partner can't
+// make us allocate without bound, and a broken message is always consumed as a
+// whole, so the stream never gets out of step.
+use net::NetError;
+use std::io::{ErrorKind, Read, Write};
+
+/// The largest message that is sent or accepted, in bytes
+pub const MAX_LEN: usize = 64 * 1024;
+
+const HEADER_LEN: usize = 4;
+
+/// Writes the payload as a single frame
+pub fn write<W: Write>(writer: &mut W, payload: &[u8]) -> Result<(), NetError> {
+    if payload.len() > MAX_LEN {
+        return Err(too_long(payload.len()));
+    }
+    // header and payload in one go, so that frames of different threads don't interleave
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads the payload of the next frame
+pub fn read<R: Read>(reader: &mut R) -> Result<Vec<u8>, NetError> {
+    // the partner may leave between two frames, but not in the middle of one
+    let mut header = [0; HEADER_LEN];
+    let mut filled = 0;
+    while filled < HEADER_LEN {
+        match reader.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Err(NetError::Disconnected),
+            Ok(0) => return Err(truncated()),
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_LEN {
+        // not worth waiting for, the connection is closed after a protocol violation
+        return Err(too_long(len));
+    }
+
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => truncated(),
+        _ => NetError::from(e),
+    })?;
+    Ok(payload)
+}
+
+fn too_long(len: usize) -> NetError {
+    NetError::Protocol(format!("message of {} bytes exceeds the limit of {} bytes", len, MAX_LEN))
+}
+
+fn truncated() -> NetError {
+    NetError::Protocol("the connection ended in the middle of a message".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+    use std::io;
+
+    /// A connection that fails with the given error after the given bytes
+    struct Failing<'a>(&'a [u8], ErrorKind);
+
+    impl<'a> Read for Failing<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::new(self.1, "failed"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    fn protocol<T: fmt::Debug>(result: Result<T, NetError>) -> String {
+        match result {
+            Err(NetError::Protocol(msg)) => msg,
+            other => panic!("expected a protocol violation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut stream = Vec::new();
+        write(&mut stream, b"hello").unwrap();
+        write(&mut stream, b"").unwrap();
+        write(&mut stream, &[7; MAX_LEN]).unwrap();
+        assert_eq!(&stream[..HEADER_LEN], &[0, 0, 0, 5]);
+
+        let mut reader = &stream[..];
+        assert_eq!(read(&mut reader).unwrap(), b"hello");
+        assert_eq!(read(&mut reader).unwrap(), b"");
+        assert_eq!(read(&mut reader).unwrap(), vec![7; MAX_LEN]);
+        assert!(matches!(read(&mut reader), Err(NetError::Disconnected)));
+    }
+
+    #[test]
+    fn oversized_frames() {
+        let mut stream = Vec::new();
+        assert!(protocol(write(&mut stream, &[0; MAX_LEN + 1])).contains("exceeds the limit"));
+        assert!(stream.is_empty());
+
+        // rejected right after the header, without waiting for or allocating 4 GiB
+        let mut reader = &[0xff, 0xff, 0xff, 0xff][..];
+        assert!(protocol(read(&mut reader)).contains("exceeds the limit"));
+    }
+
+    #[test]
+    fn truncated_frames() {
+        let mut reader = &[0, 0][..];
+        assert!(protocol(read(&mut reader)).contains("middle of a message"));
+        let mut reader = &[0, 0, 0, 5, b'h', b'i'][..];
+        assert!(protocol(read(&mut reader)).contains("middle of a message"));
+    }
+
+    #[test]
+    fn failing_connections() {
+        let mut reset = Failing(&[], ErrorKind::ConnectionReset);
+        assert!(matches!(read(&mut reset), Err(NetError::Disconnected)));
+        let mut failed = Failing(&[0, 0, 0, 5, b'h'], ErrorKind::Other);
+        assert!(matches!(read(&mut failed), Err(NetError::Io(..))));
+    }
+}
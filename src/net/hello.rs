@@ -0,0 +1,236 @@
+// Handshake: right after connecting both sides send a `Hello` with the version of
+// the protocol, the name of the player and the features they support. The host
+// also sends the rules of the game. Hellos are encoded on their own instead of as
+// a MessageType and start with the version, so that every version of the game
+// can read the version of the other side and refuse it with a clear explanation.
+// The host then answers with `Welcome` and the features both sides support, or
+// with `Refused` and the reason.
+// A host that offers LOBBY isn't a player but a lobby, see the lobby module:
+// the game starts once the client found an opponent there.
+use bincode::serde::{deserialize_from, serialize};
+use bincode::SizeLimit;
+use model::types::ShipType;
+use net::{self, frame, NetError};
+use net::types::MessageType;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Changes whenever messages are added, removed or reordered
+pub const PROTOCOL_VERSION: u32 = 4;
+
+/// How long the host waits for the Hello of a client
+const HELLO_TIMEOUT_MS: u64 = 5000;
+
+/// Both boards stay secret until the end of the game, see net::commit
+pub const HIDDEN_BOARDS: &str = "hidden-boards";
+
+/// The host is a lobby for many games, see the lobby module
+pub const LOBBY: &str = "lobby";
+
+/// Spectators may watch the game, see spectate
+pub const SPECTATORS: &str = "spectators";
+
+/// The player lets spectators see both fleets if the opponent does too, see spectate
+pub const REVEAL: &str = "reveal";
+
+/// The features this version supports as a client
+pub const CAPABILITIES: &[&str] = &[HIDDEN_BOARDS, LOBBY, SPECTATORS];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hello {
+    // always the first field, see above
+    pub version: u32,
+    pub name: String,
+    // only sent by the host
+    pub rules: Option<Rules>,
+    pub capabilities: Vec<String>,
+    // only watches the game, see spectate
+    pub spectator: bool,
+}
+
+/// The game as set up by the host
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rules {
+    pub dim: usize,
+    pub fleet: Vec<ShipType>,
+    pub turns: Turns,
+    // requires HIDDEN_BOARDS
+    pub hidden: bool,
+}
+
+/// Who fires next
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Turns {
+    // the host draws the first player, then one shot each
+    Alternating,
+}
+
+impl Hello {
+    /// The Hello of this version of the game offering the given features
+    pub fn new(name: &str, rules: Option<Rules>, capabilities: &[&str]) -> Hello {
+        Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+            rules: rules,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+            spectator: false,
+        }
+    }
+
+    /// Whether the given feature is offered
+    pub fn offers(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// Returns the features both sides support, or why the client can't join the game
+pub fn negotiate(host: &Hello, client: &Hello) -> Result<Vec<String>, String> {
+    let hidden = host.rules.as_ref().map(|rules| rules.hidden).unwrap_or(false);
+    if client.spectator && !host.offers(SPECTATORS) {
+        return Err("the server doesn't allow spectators".to_string());
+    }
+    if hidden && !client.spectator && !client.offers(HIDDEN_BOARDS) {
+        return Err("the game is played with hidden boards, which the client doesn't support"
+            .to_string());
+    }
+    if host.offers(LOBBY) && !client.spectator && !client.offers(LOBBY) {
+        return Err("the server is a lobby, which the client doesn't support".to_string());
+    }
+    Ok(host.capabilities.iter()
+        .filter(|c| client.offers(c))
+        .cloned()
+        .collect())
+}
+
+/// Exchanges the hellos with a client as the host and checks whether they can
+/// play together. If not, the client is told why.
+/// Returns the Hello of the client and the features both sides support.
+/// A client that doesn't send its Hello in time is given up, so it can't stall the host.
+pub fn greet(stream: &mut TcpStream, host: &Hello) -> Result<(Hello, Vec<String>), NetError> {
+    send(stream, host)?;
+    stream.set_read_timeout(Some(Duration::from_millis(HELLO_TIMEOUT_MS)))?;
+    let received = receive(stream);
+    stream.set_read_timeout(None)?;
+    let client = match received {
+        Err(NetError::Io(_, ref e))
+            if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+            return Err(NetError::Handshake(format!(
+                "the other side didn't send its hello within {} ms", HELLO_TIMEOUT_MS
+            )));
+        },
+        received => received?,
+    };
+    match negotiate(host, &client) {
+        Ok(capabilities) => Ok((client, capabilities)),
+        Err(reason) => {
+            net::send(stream, MessageType::Refused(reason.clone()))?;
+            Err(NetError::Handshake(format!("refused {}: {}", client.name, reason)))
+        },
+    }
+}
+
+/// Sends the Hello
+pub fn send<W: Write>(stream: &mut W, hello: &Hello) -> Result<(), NetError> {
+    let payload = serialize(hello, SizeLimit::Bounded(frame::MAX_LEN as u64))
+        .map_err(|e| NetError::Protocol(format!("hello can't be sent: {}", e)))?;
+    frame::write(stream, &payload)
+}
+
+/// Waits for the Hello of the other side and checks that it speaks the same protocol
+pub fn receive<R: Read>(stream: &mut R) -> Result<Hello, NetError> {
+    let payload = frame::read(stream)?;
+    let limit = SizeLimit::Bounded(payload.len() as u64);
+    let version: u32 = deserialize_from(&mut &payload[..], limit)
+        .map_err(|_| NetError::Handshake("the other side isn't a battleship game".to_string()))?;
+    if version != PROTOCOL_VERSION {
+        return Err(NetError::Handshake(format!(
+            "the other side speaks protocol version {}, but this game speaks version {}, {}",
+            version, PROTOCOL_VERSION, "please use the same version of battleship"
+        )));
+    }
+    deserialize_from(&mut &payload[..], limit)
+        .map_err(|e| NetError::Handshake(format!("invalid hello: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(hidden: bool, capabilities: &[&str]) -> Hello {
+        let rules = Rules { dim: 10, fleet: Vec::new(), turns: Turns::Alternating, hidden: hidden };
+        Hello::new("Host", Some(rules), capabilities)
+    }
+
+    #[test]
+    fn same_version() {
+        let mut stream = Vec::new();
+        send(&mut stream, &host(true, CAPABILITIES)).unwrap();
+        let hello = receive(&mut &stream[..]).unwrap();
+        assert_eq!(hello.version, PROTOCOL_VERSION);
+        assert_eq!(hello.name, "Host");
+        assert!(hello.rules.as_ref().unwrap().hidden);
+        assert!(CAPABILITIES.iter().all(|c| hello.offers(c)));
+    }
+
+    #[test]
+    fn older_version() {
+        let mut old = host(false, &[]);
+        old.version = PROTOCOL_VERSION - 1;
+        let mut stream = Vec::new();
+        send(&mut stream, &old).unwrap();
+        match receive(&mut &stream[..]) {
+            Err(NetError::Handshake(msg)) => {
+                assert!(msg.contains(&format!("version {}", PROTOCOL_VERSION - 1)), "{}", msg)
+            },
+            other => panic!("expected a refused handshake, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn not_a_hello() {
+        let mut stream = Vec::new();
+        frame::write(&mut stream, &[1]).unwrap();
+        assert!(matches!(receive(&mut &stream[..]), Err(NetError::Handshake(_))));
+    }
+
+    #[test]
+    fn common_capabilities() {
+        let client = Hello::new("Client", None, &[HIDDEN_BOARDS, SPECTATORS]);
+        let lobby = host(false, &[HIDDEN_BOARDS, LOBBY]);
+        // the client doesn't know LOBBY, so it can't join a lobby
+        assert!(negotiate(&lobby, &client).is_err());
+        let server = host(false, &[HIDDEN_BOARDS, SPECTATORS]);
+        let common = vec![HIDDEN_BOARDS.to_string(), SPECTATORS.to_string()];
+        assert_eq!(negotiate(&server, &client), Ok(common));
+    }
+
+    #[test]
+    fn disjoint_capabilities() {
+        let client = Hello::new("Client", None, &[]);
+        let open = host(false, &[HIDDEN_BOARDS, SPECTATORS]);
+        assert_eq!(negotiate(&open, &client), Ok(Vec::new()));
+        // hidden boards are a rule of the game, not an option
+        let hidden = host(true, &[HIDDEN_BOARDS]);
+        assert!(negotiate(&hidden, &client).is_err());
+    }
+
+    #[test]
+    fn spectators_need_the_permission_of_the_host() {
+        let mut spectator = Hello::new("Spectator", None, CAPABILITIES);
+        spectator.spectator = true;
+        assert!(negotiate(&host(true, &[HIDDEN_BOARDS]), &spectator).is_err());
+        assert!(negotiate(&host(true, &[HIDDEN_BOARDS, SPECTATORS]), &spectator).is_ok());
+    }
+
+    #[test]
+    fn reveal_needs_both_players() {
+        let server = host(false, &[SPECTATORS, REVEAL]);
+        let shy = Hello::new("Client", None, &[SPECTATORS]);
+        assert_eq!(negotiate(&server, &shy), Ok(vec![SPECTATORS.to_string()]));
+        let open = Hello::new("Client", None, &[SPECTATORS, REVEAL]);
+        let common = vec![SPECTATORS.to_string(), REVEAL.to_string()];
+        assert_eq!(negotiate(&server, &open), Ok(common));
+        assert_eq!(negotiate(&host(false, &[SPECTATORS]), &open), Ok(vec![SPECTATORS.to_string()]));
+    }
+}
@@ -1,14 +1,119 @@
+pub mod commit;
+pub mod frame;
+pub mod hello;
 pub mod types;
 
-use bincode::serde::serialize_into;
+use bincode::serde::{deserialize_from, serialize, DeserializeError, SerializeError};
 use bincode::SizeLimit;
-use std::net::TcpStream;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+
+/// Everything that can end a networked game early
+#[derive(Debug)]
+pub enum NetError {
+    // what failed and why, e.g. the port is in use or the connection was refused
+    Io(String, io::Error),
+    // the partner sent a message that doesn't fit the state of the game
+    Protocol(String),
+    // the partners couldn't agree on a game
+    Handshake(String),
+    // the partner quit or the connection dropped
+    Disconnected,
+}
+
+impl NetError {
+    /// The exit code of the process, following sysexits.h
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            NetError::Io(..) => 74,
+            NetError::Protocol(_) | NetError::Handshake(_) => 76,
+            NetError::Disconnected => 69,
+        }
+    }
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetError::Io(ref what, ref e) => write!(f, "{}: {}", what, e),
+            NetError::Protocol(ref msg) => write!(f, "protocol violation: {}", msg),
+            NetError::Handshake(ref msg) => write!(f, "handshake failed: {}", msg),
+            NetError::Disconnected => write!(f, "the connection was closed"),
+        }
+    }
+}
+
+impl Error for NetError {}
+
+impl From<io::Error> for NetError {
+    fn from(e: io::Error) -> NetError {
+        if disconnected(&e) {
+            NetError::Disconnected
+        } else {
+            NetError::Io("connection failed".to_string(), e)
+        }
+    }
+}
+
+/// Whether the error means that the partner is gone
+fn disconnected(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe
+    )
+}
+
+/// Looks up the addresses of the given host name, IPv4 or IPv6 address
+pub fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, NetError> {
+    // IPv6 addresses may be given in brackets, like in URLs
+    let name = host.trim_start_matches('[').trim_end_matches(']');
+    let context = || format!("could not resolve '{}'", host);
+    let addresses: Vec<_> = (name, port).to_socket_addrs()
+        .map_err(|e| NetError::Io(context(), e))?
+        .collect();
+    if addresses.is_empty() {
+        let e = io::Error::new(ErrorKind::NotFound, "the host has no address");
+        return Err(NetError::Io(context(), e));
+    }
+    Ok(addresses)
+}
+
+/// Formats host and port for messages, with brackets around IPv6 addresses
+pub fn display_address(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
 
 /// Send a message to connected partner on stream
-pub fn send(mut stream: &mut TcpStream, msg: types::MessageType) {
-    serialize_into(
-        stream,
-        &msg,
-        SizeLimit::Infinite
-    ).unwrap();
+pub fn send(stream: &mut TcpStream, msg: types::MessageType) -> Result<(), NetError> {
+    let payload = serialize(&msg, SizeLimit::Bounded(frame::MAX_LEN as u64)).map_err(|e| match e {
+        SerializeError::IoError(e) => NetError::from(e),
+        e => NetError::Protocol(format!("message can't be sent: {}", e)),
+    })?;
+    frame::write(stream, &payload)
+}
+
+/// Waits for the next message of the connected partner
+pub fn receive(stream: &mut TcpStream) -> Result<types::MessageType, NetError> {
+    let payload = frame::read(stream)?;
+    let mut reader = &payload[..];
+    let msg = deserialize_from(&mut reader, SizeLimit::Bounded(payload.len() as u64))
+        .map_err(|e| match e {
+            DeserializeError::IoError(_) => {
+                NetError::Protocol("message ends too early".to_string())
+            },
+            e => NetError::Protocol(format!("invalid message: {}", e)),
+        })?;
+    if !reader.is_empty() {
+        return Err(NetError::Protocol("message is longer than expected".to_string()));
+    }
+    Ok(msg)
 }
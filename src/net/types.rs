@@ -1,12 +1,15 @@
-use model::types::SubField;
+use model::types::{Board, Shot};
+use spectate::Snapshot;
+use std::net::{IpAddr, Ipv4Addr};
 
 ///////////////////////////
 ///     Constants       ///
 ///////////////////////////
-pub const LOCALHOST: &'static str = "127.0.0.1";
+pub const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum MessageType {
+    List,
     Lost,
     Ping,
     Quit,
@@ -17,11 +20,28 @@ pub enum MessageType {
     TurnHost,
     Unexpected,
     Won,
-    Board([[SubField; 10]; 10]),
+    Board(Board),
+    // the name of the player and the message, see chat
+    Chat(String, String),
+    Commit(Vec<u8>),
+    Create(String),
+    // a shot for the spectators
+    Fired(Shot),
     Hit(usize),
-    Login(String),
+    Join(String),
     Miss(usize),
+    Refused(String),
+    Reveal(Vec<u8>, Board),
+    // the rooms with their waiting players and the players without a room
+    Rooms(Vec<(String, String)>, Vec<String>),
     Shoot(String),
+    // the whole game for the spectators
+    Snapshot(Snapshot),
+    // the name of the opponent found in the lobby
+    Start(String),
+    Sunk(usize, String),
     Text(String),
-    Welcome(String, String),
+    // the spectator wants to watch the match in the given room of a lobby
+    Watch(String),
+    Welcome(String, Vec<String>),
 }
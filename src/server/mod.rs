@@ -1,296 +1,467 @@
 extern crate chan;
-extern crate rand;
 
-use bincode;
-use bincode::serde::deserialize_from;
+use chat;
 use model;
-use model::types::{Board, Player, PlayerType, SubField};
-use net::{self, types};
-use net::types::{MessageType};
+use model::ai::Strategy;
+use model::engine::GameEngine;
+use model::replay::Replay;
+use model::types::{Board, Player, PlayerId, PlayerType, ShipType, ShotOutcome};
+use net::{self, hello, NetError};
+use net::types::MessageType;
+use spectate::Audience;
 use util;
-use std::net::{Shutdown, TcpListener, TcpStream};
+use rand::{Rng, StdRng};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::thread;
 use term_painter::ToStyle;
 use term_painter::Color::*;
 
-#[derive(Debug)]
-enum CurrentPlayer {
-    Host,
-    Client,
-}
+const HOST: PlayerId = PlayerId::One;
+const CLIENT: PlayerId = PlayerId::Two;
 
 pub struct Server {
-    pub ip: &'static str,
+    // 0.0.0.0 or :: to accept players from other machines
+    pub ip: IpAddr,
     pub port: u16,
     pub host_name: String,
-    pub host_board: [[SubField; 10]; 10],
-    pub board_dim: u8,
+    pub host_board: Board,
+    pub board_dim: usize,
+    pub fleet: Vec<ShipType>,
+    // Both boards stay hidden, see net::commit
+    pub hidden: bool,
+    // others may watch the game, see spectate
+    pub spectators: bool,
+    // spectators see both fleets if the client agrees, never with hidden boards
+    pub reveal: bool,
+    // plays instead of the host, see bot
+    pub bot: Option<Box<dyn Strategy>>,
+    // the source of all random decisions, see model::game_rng
+    pub rng: StdRng,
 }
 
 /// Initialize and prepare game
-pub fn init(server: Server) {
-    let listener = TcpListener::bind((server.ip, server.port)).unwrap();
+pub fn init(server: Server) -> Result<(), NetError> {
+    let address = SocketAddr::new(server.ip, server.port);
+    let listener = TcpListener::bind(address)
+        .map_err(|e| NetError::Io(format!("could not listen on {}", address), e))?;
 
-    // accept one incoming connection
-    let (client_conn, _) = listener.accept().unwrap();
-    let mut client_stream = client_conn.try_clone().unwrap();
+    // the hello of the host, see net::hello
+    let rules = hello::Rules {
+        dim: server.board_dim,
+        fleet: server.fleet.clone(),
+        turns: hello::Turns::Alternating,
+        hidden: server.hidden,
+    };
+    let mut capabilities = vec![hello::HIDDEN_BOARDS];
+    if server.spectators {
+        capabilities.push(hello::SPECTATORS);
+    }
+    if server.reveal && !server.hidden {
+        capabilities.push(hello::REVEAL);
+    }
+    let host_hello = hello::Hello::new(&server.host_name, Some(rules), &capabilities);
+    let audience = Audience::default();
 
-    // add CTRL+C system hook, so that connection partner is informed about disconnect
-    let client_stream_clone = client_stream.try_clone().unwrap();
-    ::ctrlc::set_handler(move || {
-        net::send(&mut client_stream_clone.try_clone().unwrap(), MessageType::Quit);
-        client_stream_clone.shutdown(Shutdown::Both).expect("shutdown call failed");
-    }).expect("Error setting Ctrl+C handler");
+    // accept one incoming connection, spectators may come earlier
+    let (mut client_stream, client_hello, capabilities) = loop {
+        let mut stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                Red.with(|| println!("Could not accept a connection: {}", e));
+                continue;
+            },
+        };
+        // a peer that fails the handshake doesn't end the server
+        let (client_hello, capabilities) = match hello::greet(&mut stream, &host_hello) {
+            Ok(greeted) => greeted,
+            Err(e) => {
+                Red.with(|| println!("{}", e));
+                let _ = stream.shutdown(Shutdown::Both);
+                continue;
+            },
+        };
+        if !client_hello.spectator {
+            // REVEAL is only negotiated if both players offered it
+            if capabilities.iter().any(|c| c == hello::REVEAL) {
+                audience.reveal();
+            }
+            break (stream, client_hello, capabilities);
+        }
+        if let Err(e) = admit(stream, capabilities, &audience) {
+            Red.with(|| println!("{} can't watch: {}", client_hello.name, e));
+        }
+    };
 
-    // welcome client
-    net::send(
-        &mut client_stream,
-        MessageType::Welcome(
-            "Welcome stranger, let me sink your ships!".to_string(),
-            server.host_name.clone())
-    );
-
-    // wait for client to send his name
-    let recv: Result<types::MessageType, _> =
-        deserialize_from(&mut client_stream, bincode::SizeLimit::Infinite);
-    let client_name = match recv {
-        Ok(received) => {
-            match received {
-                MessageType::Login(name) => {
-                    name
-                },
-                MessageType::Quit => {
-                    println!("Client closed connection.");
-                    return
+    // later connections can only watch
+    let spectators = audience.clone();
+    thread::spawn(move || {
+        for conn in listener.incoming() {
+            let mut stream = match conn {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            match hello::greet(&mut stream, &host_hello) {
+                Ok((ref hello, _)) if !hello.spectator => {
+                    let reason = "the game has already started, you can only watch".to_string();
+                    let _ = net::send(&mut stream, MessageType::Refused(reason));
                 },
-                _ => {
-                    // unexpected packet
-                    net::send(&mut client_stream, MessageType::Unexpected);
-                    net::send(&mut client_stream, MessageType::Quit);
-                    panic!("Received unexpected packet!")
+                Ok((_, capabilities)) => {
+                    let _ = admit(stream, capabilities, &spectators);
                 },
+                Err(_) => {},
             }
-        },
+        }
+    });
+
+    // add CTRL+C system hook, so that connection partner is informed about disconnect
+    let client_stream_clone = client_stream.try_clone()?;
+    let handler = ::ctrlc::set_handler(move || {
+        if let Ok(mut stream) = client_stream_clone.try_clone() {
+            let _ = net::send(&mut stream, MessageType::Quit);
+        }
+        let _ = client_stream_clone.shutdown(Shutdown::Both);
+    });
+    if let Err(e) = handler {
+        Red.with(|| println!("Could not set the Ctrl+C handler: {:?}", e));
+    }
+
+    let result = serve(server, &mut client_stream, client_hello, capabilities, &audience);
+    audience.close();
+    // tell the client that the game is over, unless it's already gone or
+    // doesn't understand our messages
+    match result {
+        Err(NetError::Disconnected) | Err(NetError::Handshake(_)) | Ok(()) => {},
         Err(_) => {
-            Red.with(|| println!("ERROR while waiting for Login"));
-            net::send(&mut client_stream, MessageType::Quit);
-            client_stream.shutdown(Shutdown::Both).expect("shutdown call failed");
-            return;
+            let _ = net::send(&mut client_stream, MessageType::Quit);
         },
-    };
+    }
+    let _ = client_stream.shutdown(Shutdown::Both);
+    result?;
+
+    Yellow.with(|| println!("\nBye."));
+    Ok(())
+}
+
+/// Welcomes a spectator, see spectate
+fn admit(
+    mut stream: TcpStream,
+    capabilities: Vec<String>,
+    audience: &Audience
+) -> Result<(), NetError> {
+    net::send(
+        &mut stream,
+        MessageType::Welcome("Welcome, enjoy the game!".to_string(), capabilities)
+    )?;
+    audience.join(stream)
+}
+
+/// Welcomes the client and plays the game with it
+fn serve(
+    server: Server,
+    client_stream: &mut TcpStream,
+    client_hello: hello::Hello,
+    capabilities: Vec<String>,
+    audience: &Audience
+) -> Result<(), NetError> {
+    net::send(
+        client_stream,
+        MessageType::Welcome("Welcome stranger, let me sink your ships!".to_string(), capabilities)
+    )?;
+    let client_name = client_hello.name;
+    chat::open(client_stream, &server.host_name, Some(&client_name))?;
 
     // create players
     let host = Player {
-        own_board: server.host_board.clone(),
-        op_board: Board::init(),
+        own_board: Board::init(server.board_dim),
+        op_board: Board::init(server.board_dim),
         player_type: PlayerType::Human,
         name: server.host_name,
-        capacity: Board::targets(&server.host_board),
+        capacity: 0,
     };
 
     let client = Player {
-        own_board: Board::init(),
-        op_board: Board::init(),
+        own_board: Board::init(server.board_dim),
+        op_board: Board::init(server.board_dim),
         player_type: PlayerType::Human,
         name: client_name,
-        capacity: Board::targets(&Board::init()),
+        capacity: 0,
     };
 
     // start game
-    start(host, client, client_stream);
-
-    Yellow.with(|| println!("\nBye."));
+    let mut engine = GameEngine::new(host, client, server.fleet);
+    if !server.host_board.empty() {
+        engine.set_board(HOST, &server.host_board).map_err(|e| {
+            NetError::Handshake(format!("invalid board configuration of the host: {}", e))
+        })?;
+    }
+    start(engine, server.hidden, server.bot, server.rng, client_stream, audience)
 }
 
 /// Starting the game with given parameters
-fn start(mut host: Player, mut client: Player, mut stream: TcpStream) {
+fn start(
+    mut engine: GameEngine,
+    hidden: bool,
+    mut bot: Option<Box<dyn Strategy>>,
+    mut rng: StdRng,
+    stream: &mut TcpStream,
+    audience: &Audience
+) -> Result<(), NetError> {
+    let fleet = engine.fleet().to_vec();
+
     ///////////////////////////////////////////////////////////////////////////////////////////////
     //                    Request initial board configuration from host                          //
     ///////////////////////////////////////////////////////////////////////////////////////////////
 
-    if host.capacity == 0 {
-        net::send(
-            &mut stream,
-            MessageType::Text("Server is setting its ships, please wait :)".to_string())
-        );
-        println!("Please set your ships:");
-        loop {
-            match model::place_ships(&mut host) {
-                Ok(()) => { break; },
-                Err(model::types::ErrorType::DeadEndHuman) => {
-                    Red.with(|| println!(
-                        "No suitable position left, please restart the ship placement.")
-                    );
-                    model::restart_placement(&mut host);
-                },
-                Err(_) => {
-                    Red.with(|| println!("Failed placing ships!"));
-                    net::send(&mut stream, MessageType::Quit);
-                    stream.shutdown(Shutdown::Both).expect("shutdown call failed");
-                    return
-                },
+    if engine.player(HOST).capacity == 0 {
+        if let Some(bot) = bot.as_mut() {
+            let dim = engine.player(HOST).own_board.dim();
+            let placed = bot.place(dim, &fleet, &mut rng).and_then(|board| {
+                engine.set_board(HOST, &board).map_err(|e| e.to_string())
+            });
+            if let Err(e) = placed {
+                Red.with(|| println!("The bot failed placing the ships: {}", e));
+                return net::send(stream, MessageType::Quit);
+            }
+        } else {
+            net::send(
+                stream,
+                MessageType::Text("Server is setting its ships, please wait :)".to_string())
+            )?;
+            println!("Please set your ships:");
+            loop {
+                match model::place_ships(&mut engine, HOST) {
+                    Ok(()) => { break; },
+                    Err(model::types::ErrorType::DeadEndHuman) => {
+                        Red.with(|| println!(
+                            "No suitable position left, please restart the ship placement.")
+                        );
+                        engine.restart_placement(HOST);
+                    },
+                    Err(_) => {
+                        Red.with(|| println!("Failed placing ships!"));
+                        return net::send(stream, MessageType::Quit);
+                    },
+                }
             }
         }
     }
-    model::print_boards(&host);
+    model::print_boards(engine.player(HOST));
+    let host_placement = engine.player(HOST).own_board.clone();
 
     ///////////////////////////////////////////////////////////////////////////////////////////////
     //                    Request initial board configuration from client                        //
     ///////////////////////////////////////////////////////////////////////////////////////////////
-    net::send(&mut stream, MessageType::RequestBoard);
-
-    loop {
-        let recv: Result<types::MessageType, _> =
-            deserialize_from(&mut stream, bincode::SizeLimit::Infinite);
-        match recv {
-            Ok(received) => {
-                match received {
-                    MessageType::Board(vec) => {
-                        client.set_board(vec);
-                        break;
-                    },
-                    MessageType::Quit => {
-                        println!("Client closed connection.");
-                        return
-                    },
-                    _ => {
-                        Red.with(|| println!("Unexpected packet!"));
-                        net::send(&mut stream, MessageType::Quit);
-                        stream.shutdown(Shutdown::Both).expect("shutdown call failed");
-                        return
-                    },
-                }
-            },
-            Err(_) => {
-                Red.with(|| println!("ERROR board"));
-                net::send(&mut stream, MessageType::Quit);
-                stream.shutdown(Shutdown::Both).expect("shutdown call failed");
-                return
-            },
-        };
+    // With hidden boards only the commitments are exchanged.
+    let salt = net::commit::salt();
+    let mut client_commitment = Vec::new();
+    if hidden {
+        let commitment = net::commit::commitment(&host_placement, &salt);
+        net::send(stream, MessageType::Commit(commitment))?;
+    } else {
+        net::send(stream, MessageType::RequestBoard)?;
+    }
+
+    match chat::receive(stream)? {
+        MessageType::Commit(commitment) if hidden => {
+            client_commitment = commitment;
+            engine.set_hidden(CLIENT);
+        },
+        MessageType::Board(board) if !hidden => {
+            // don't rely on the ships announced by the client
+            if let Err(e) = engine.set_board(CLIENT, &board) {
+                let location = e.field()
+                    .map(|idx| format!("{}: ", board.coordinate(idx)))
+                    .unwrap_or_default();
+                return Err(NetError::Protocol(
+                    format!("the client sent an invalid board: {}{}", location, e)
+                ));
+            }
+        },
+        MessageType::Quit => return Err(NetError::Disconnected),
+        _ => return Err(NetError::Protocol("expected the board of the client".to_string())),
     }
 
     ///////////////////////////////////////////////////////////////////////////////////////////////
     //                              Choose random start player                                   //
     ///////////////////////////////////////////////////////////////////////////////////////////////
-    let mut current_player = if rand::random() {
-        CurrentPlayer::Host
-    } else {
-        CurrentPlayer::Client
-    };
-    // println!("Starting player: {:?}", Yellow.paint(&current_player));
+    engine.set_turn(if rng.gen() { HOST } else { CLIENT });
+    audience.show(&engine);
+
+    // The answers of the client to the shots of the host (hidden boards only).
+    let mut answers = Vec::new();
 
     ///////////////////////////////////////////////////////////////////////////////////////////////
     //                             Take turns while not ended                                    //
     ///////////////////////////////////////////////////////////////////////////////////////////////
-    loop {
-        match current_player {
-            CurrentPlayer::Host => {
+    while engine.winner().is_none() {
+        match engine.current_turn() {
+            HOST => {
                 // inform Client that its the turn of Host
-                net::send(&mut stream, MessageType::TurnHost);
+                net::send(stream, MessageType::TurnHost)?;
 
                 // wait for input from Host
                 Yellow.with(|| println!("It's your turn!"));
-                let mut coord;
-                loop {
-                    Yellow.with(|| println!("Please enter a valid coordinate: "));
-                    coord = util::read_string();
-                    if ::model::valid_coordinate(&coord) {
-                        break;
-                    }
-                    Red.with(|| print!("Invalid coordinate! "));
-                }
+                let coord_id = match bot {
+                    Some(ref mut bot) => match bot.target(&engine.player(HOST).op_board, &mut rng) {
+                        Ok(id) => id,
+                        Err(e) => {
+                            Red.with(|| println!("The bot forfeits the game: {}", e));
+                            engine.resign(HOST);
+                            break;
+                        },
+                    },
+                    None => loop {
+                        Yellow.with(|| println!("Please enter a valid coordinate: "));
+                        let input = util::read_string();
+                        if let Some(id) = engine.player(HOST).op_board.get_index(&input) {
+                            break id;
+                        }
+                        Red.with(|| print!("Invalid coordinate! "));
+                    },
+                };
                 // modify boards
-                let coord_id = Board::get_index(&coord);
-                match ::model::match_move(&mut host, &mut client, coord_id) {
-                    SubField::Hit => {
-                        net::send(&mut stream, MessageType::Hit(coord_id));
-                        model::print_boards(&host);
-                        current_player = CurrentPlayer::Client;
-                    }
-                    SubField::Miss => {
-                        net::send(&mut stream, MessageType::Miss(coord_id));
-                        model::print_boards(&host);
-                        current_player = CurrentPlayer::Client;
-                    }
-                    _ => {}
+                let outcome = if hidden {
+                    // the client answers the shot at its board
+                    let op_board = &engine.player(HOST).op_board;
+                    let outcome = ask_client(stream, op_board, coord_id)?;
+                    model::announce(&outcome);
+                    engine.apply(coord_id, &outcome).map_err(|e| {
+                        NetError::Protocol(format!("invalid answer of the client: {:?}", e))
+                    })?;
+                    answers.push((coord_id, outcome.clone()));
+                    outcome
+                } else {
+                    model::repeated_shot(&engine.player(HOST).op_board, coord_id);
+                    let outcome = engine.fire(coord_id).map_err(|e| {
+                        NetError::Protocol(format!("shot at the client's board failed: {:?}", e))
+                    })?;
+                    model::announce(&outcome);
+                    net::send(stream, match outcome {
+                        ShotOutcome::Hit => MessageType::Hit(coord_id),
+                        ShotOutcome::Sunk(ref name) => MessageType::Sunk(coord_id, name.clone()),
+                        ShotOutcome::Miss => MessageType::Miss(coord_id),
+                    })?;
+                    outcome
+                };
+                audience.fired(&engine);
+                if let Some(ref mut bot) = bot {
+                    bot.record(&engine.player(HOST).op_board, coord_id, &outcome);
                 }
-
-                // if Host won: send message to Client, end game
-                if ::model::game_over(&client) {
-                    net::send(&mut stream, MessageType::Lost);
-                    Yellow.with(|| println!("Congratulations, you won the game :)"));
-                    break;
+                model::print_boards(engine.player(HOST));
+                if let ShotOutcome::Sunk(name) = outcome {
+                    Green.with(|| println!("You sank the {}!", name));
                 }
             }
-            CurrentPlayer::Client => {
+            CLIENT => {
                 println!(
                     "{} {} {}",
                     Cyan.paint("Wait for"),
-                    Yellow.paint(&client.name),
+                    Yellow.paint(&engine.player(CLIENT).name),
                     Cyan.paint("to finish turn!"),
                 );
                 // inform Client that its his turn
-                net::send(&mut stream, MessageType::RequestCoord);
+                net::send(stream, MessageType::RequestCoord)?;
                 // wait for input from Client
-                let recv: Result<types::MessageType, _> =
-                    deserialize_from(&mut stream, bincode::SizeLimit::Infinite);
-                let coordinate = match recv {
-                    Ok(received) => {
-                        match received {
-                            MessageType::Shoot(coord) => {
-                                coord
-                            },
-                            MessageType::Quit => {
-                                println!("Client closed connection.");
-                                return
-                            },
-                            _ => {
-                                // unexpected packet
-                                Red.with(|| println!("Unexpected Packet"));
-                                net::send(&mut stream, MessageType::Quit);
-                                stream.shutdown(Shutdown::Both).expect("shutdown call failed");
-                                return
-                            },
-                        }
-                    },
-                    Err(_) => {
-                        Red.with(|| println!("ERROR receiving coord"));
-                        net::send(&mut stream, MessageType::Quit);
-                        stream.shutdown(Shutdown::Both).expect("shutdown call failed");
-                        return
-                    },
+                let coordinate = match chat::receive(stream)? {
+                    MessageType::Shoot(coord) => coord,
+                    MessageType::Quit => return Err(NetError::Disconnected),
+                    _ => return Err(NetError::Protocol("expected a shot".to_string())),
                 };
 
                 // modify boards
-                let coord_id = Board::get_index(&coordinate);
-                match ::model::match_move(&mut client, &mut host, coord_id) {
-                    SubField::Hit => {
-                        println!("{} hit one of your ships!", client.name);
-                        net::send(&mut stream, MessageType::Hit(coord_id));
-                        model::print_boards(&host);
-                        current_player = CurrentPlayer::Host;
+                let coord_id = engine.player(HOST).own_board.get_index(&coordinate).ok_or_else(|| {
+                    NetError::Protocol(format!("invalid coordinate '{}'", coordinate))
+                })?;
+                let outcome = engine.fire(coord_id).map_err(|e| {
+                    NetError::Protocol(format!("invalid shot of the client: {:?}", e))
+                })?;
+                audience.fired(&engine);
+                let client_name = &engine.player(CLIENT).name;
+                match outcome {
+                    ShotOutcome::Hit => {
+                        println!("{} hit one of your ships!", client_name);
+                        net::send(stream, MessageType::Hit(coord_id))?;
+                        model::print_boards(engine.player(HOST));
                     }
-                    SubField::Miss => {
-                        println!("{} missed your ships.", client.name);
-                        net::send(&mut stream, MessageType::Miss(coord_id));
-                        model::print_boards(&host);
-                        current_player = CurrentPlayer::Host;
+                    ShotOutcome::Sunk(name) => {
+                        net::send(stream, MessageType::Sunk(coord_id, name.clone()))?;
+                        model::print_boards(engine.player(HOST));
+                        Red.with(|| println!("{} sank your {}!", client_name, name));
+                    }
+                    ShotOutcome::Miss => {
+                        println!("{} missed your ships.", client_name);
+                        net::send(stream, MessageType::Miss(coord_id))?;
+                        model::print_boards(engine.player(HOST));
                     }
-                    _ => {}
                 }
+            }
+        }
+    }
+
+    let mut replay = Replay::new(&engine);
+    audience.show(&engine);
+
+    // send the result to the Client
+    if let Some(ref mut bot) = bot {
+        bot.game_over(engine.winner() == Some(HOST));
+    }
+    if engine.winner() == Some(HOST) {
+        net::send(stream, MessageType::Lost)?;
+        Yellow.with(|| println!("Congratulations, you won the game :)"));
+    } else {
+        net::send(stream, MessageType::Won)?;
+        Yellow.with(|| println!("You lost :("));
+    }
 
-                // if Client won: send message to Client, end game
-                if ::model::game_over(&host) {
-                    net::send(&mut stream, MessageType::Won);
-                    Yellow.with(|| println!("You lost :("));
-                    break;
+    ///////////////////////////////////////////////////////////////////////////////////////////////
+    //                         Reveal and check the hidden boards                                //
+    ///////////////////////////////////////////////////////////////////////////////////////////////
+    if hidden {
+        net::send(stream, MessageType::Reveal(salt, host_placement))?;
+
+        // the game is decided, so a client that doesn't reveal its board is reported below
+        match chat::receive(stream) {
+            Ok(MessageType::Reveal(client_salt, board)) => {
+                let dim = engine.player(HOST).own_board.dim();
+                let client_name = &engine.player(CLIENT).name;
+                match net::commit::check_reveal(
+                    &client_commitment, &client_salt, &board, dim, &fleet, &answers
+                ) {
+                    Ok(()) => {
+                        Green.with(|| println!(
+                            "The board of {} matches the commitment, all answers were honest.",
+                            client_name,
+                        ));
+                        replay.reveal(CLIENT, board);
+                    },
+                    Err(e) => Red.with(|| println!("{} cheated: {}!", client_name, e)),
                 }
-            }
+            },
+            _ => Red.with(|| println!(
+                "{} did not reveal the board!", engine.player(CLIENT).name
+            )),
         }
     }
 
+    replay.record();
+
     ///////////////////////////////////////////////////////////////////////////////////////////////
     //                                  Quit game                                                //
     ///////////////////////////////////////////////////////////////////////////////////////////////
-    net::send(&mut stream, MessageType::Quit);
+    // the game is over, it doesn't matter if the client already left
+    let _ = net::send(stream, MessageType::Quit);
+    Ok(())
+}
+
+/// Lets the client answer the shot at the given field of its hidden board
+fn ask_client(stream: &mut TcpStream, board: &Board, idx: usize) -> Result<ShotOutcome, NetError> {
+    net::send(stream, MessageType::Shoot(board.coordinate(idx)))?;
+
+    match chat::receive(stream)? {
+        MessageType::Hit(id) if id == idx => Ok(ShotOutcome::Hit),
+        MessageType::Miss(id) if id == idx => Ok(ShotOutcome::Miss),
+        MessageType::Sunk(id, name) if id == idx => Ok(ShotOutcome::Sunk(name)),
+        MessageType::Quit => Err(NetError::Disconnected),
+        _ => Err(NetError::Protocol("expected the answer to a shot".to_string())),
+    }
 }
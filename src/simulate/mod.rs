@@ -0,0 +1,224 @@
+// Headless games between two AI strategies and statistics about the results,
+// e.g. to check whether a change made a strategy stronger or weaker.
+use model::ai::{self, Strategy};
+use model::engine::GameEngine;
+use model::types::{Board, Player, PlayerId, PlayerType, ShipType};
+use rand::StdRng;
+
+// bucket width and maximum bar length of the histogram
+const BUCKET: usize = 5;
+const BAR: usize = 50;
+
+/// Plays the given number of games between the strategies with the given names.
+/// The starting player alternates from game to game.
+pub fn run(
+    first: &str,
+    second: &str,
+    games: usize,
+    dim: usize,
+    fleet: &[ShipType],
+    rng: &mut StdRng
+) {
+    let names = [first, second];
+    // shots the winner needed for every won game, per strategy
+    let mut shots: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+    let mut forfeits = 0;
+
+    for game in 0..games {
+        let starting = if game % 2 == 0 { PlayerId::One } else { PlayerId::Two };
+        let mut strategies = [
+            ai::strategy(first, fleet).expect("unknown strategy"),
+            ai::strategy(second, fleet).expect("unknown strategy"),
+        ];
+        let (winner, count, forfeit) = play(&mut strategies, dim, fleet, starting, rng);
+        if forfeit {
+            forfeits += 1;
+        } else {
+            shots[winner.index()].push(count);
+        }
+    }
+
+    println!("{} games on a {1}x{1} board: {2} vs. {3}", games, dim, first, second);
+    if forfeits > 0 {
+        println!("{} games ended by forfeit and are left out.", forfeits);
+    }
+    let played = games - forfeits;
+    if played == 0 {
+        return;
+    }
+
+    println!();
+    println!(
+        "{:10} {:>6} {:>22} {:>16} {:>7}",
+        "strategy", "wins", "win rate", "shots to win", "median"
+    );
+    for i in 0..2 {
+        let wins = shots[i].len();
+        let (low, high) = wilson(wins, played);
+        let shots_to_win = match mean(&shots[i]) {
+            Some((mean, ci)) => format!("{:.1} ± {:.1}", mean, ci),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:10} {:>6} {:>6.1}% ({:>5.1}-{:>5.1}%) {:>16} {:>7}",
+            names[i],
+            wins,
+            100.0 * wins as f64 / played as f64,
+            100.0 * low,
+            100.0 * high,
+            shots_to_win,
+            median(&shots[i]).map(|m| format!("{:.1}", m)).unwrap_or("-".to_string()),
+        );
+    }
+    println!("(the ranges and ± give the 95% confidence intervals)");
+
+    for i in 0..2 {
+        if !shots[i].is_empty() {
+            println!();
+            println!("Shots to win of {}:", names[i]);
+            histogram(&shots[i]);
+        }
+    }
+}
+
+/// Plays a single game without any output. Returns the winner, the number of
+/// shots the winner fired and whether the loser forfeited.
+fn play(
+    strategies: &mut [Box<dyn Strategy>; 2],
+    dim: usize,
+    fleet: &[ShipType],
+    starting: PlayerId,
+    rng: &mut StdRng
+) -> (PlayerId, usize, bool) {
+    let player = |name: &str| Player {
+        own_board: Board::init(dim),
+        op_board: Board::init(dim),
+        player_type: PlayerType::AI,
+        name: name.to_string(),
+        capacity: 0,
+    };
+    let mut engine = GameEngine::new(player("first"), player("second"), fleet.to_vec());
+    engine.set_turn(starting);
+    let mut shots = [0, 0];
+
+    for &id in &[PlayerId::One, PlayerId::Two] {
+        let placed = strategies[id.index()].place(dim, fleet, rng).and_then(|board| {
+            engine.set_board(id, &board).map_err(|e| e.to_string())
+        });
+        if placed.is_err() {
+            return (id.other(), 0, true);
+        }
+    }
+
+    while engine.winner().is_none() {
+        let id = engine.current_turn();
+        let strategy = &mut strategies[id.index()];
+        // a strategy that keeps firing at the same fields would never finish
+        if shots[id.index()] >= dim * dim {
+            return (id.other(), shots[id.other().index()], true);
+        }
+        let idx = match strategy.target(&engine.player(id).op_board, rng) {
+            Ok(idx) => idx,
+            Err(_) => return (id.other(), shots[id.other().index()], true),
+        };
+        let outcome = match engine.fire(idx) {
+            Ok(outcome) => outcome,
+            Err(_) => return (id.other(), shots[id.other().index()], true),
+        };
+        shots[id.index()] += 1;
+        strategy.record(&engine.player(id).op_board, idx, &outcome);
+    }
+
+    let winner = engine.winner().unwrap();
+    strategies[winner.index()].game_over(true);
+    strategies[winner.other().index()].game_over(false);
+    (winner, shots[winner.index()], false)
+}
+
+/// Returns the 95% confidence interval of a win rate (Wilson score interval)
+fn wilson(wins: usize, games: usize) -> (f64, f64) {
+    let z = 1.96f64;
+    let n = games as f64;
+    let p = wins as f64 / n;
+    let denominator = 1.0 + z * z / n;
+    let center = (p + z * z / (2.0 * n)) / denominator;
+    let half = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+    ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+/// Returns the mean and the half width of its 95% confidence interval
+fn mean(values: &[usize]) -> Option<(f64, f64)> {
+    if values.is_empty() {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<usize>() as f64 / n;
+    if values.len() < 2 {
+        return Some((mean, 0.0));
+    }
+    let variance = values.iter()
+        .map(|&v| (v as f64 - mean).powi(2))
+        .sum::<f64>() / (n - 1.0);
+    Some((mean, 1.96 * (variance / n).sqrt()))
+}
+
+/// Returns the median of the given values, the mean of the two middle values
+/// for an even number of values
+fn median(values: &[usize]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort();
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(sorted[middle] as f64),
+        _ => Some((sorted[middle - 1] + sorted[middle]) as f64 / 2.0),
+    }
+}
+
+/// Prints a histogram of the given values
+fn histogram(values: &[usize]) {
+    let min = values.iter().min().unwrap() / BUCKET;
+    let max = values.iter().max().unwrap() / BUCKET;
+    let mut counts = vec![0; max - min + 1];
+    for &v in values {
+        counts[v / BUCKET - min] += 1;
+    }
+    let highest = *counts.iter().max().unwrap();
+
+    for (i, &count) in counts.iter().enumerate() {
+        let from = (min + i) * BUCKET;
+        let bar = (count * BAR + highest - 1) / highest;
+        println!("{:>4}-{:<4} {:>6} {}", from, from + BUCKET - 1, count, "#".repeat(bar));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_and_even_amounts() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[7]), Some(7.0));
+        assert_eq!(median(&[9, 1, 5]), Some(5.0));
+        assert_eq!(median(&[40, 10, 30, 20]), Some(25.0));
+        assert_eq!(median(&[3, 4]), Some(3.5));
+    }
+
+    #[test]
+    fn mean_and_confidence() {
+        assert_eq!(mean(&[]), None);
+        assert_eq!(mean(&[5]), Some((5.0, 0.0)));
+        let (mean, ci) = mean(&[2, 4]).unwrap();
+        assert_eq!(mean, 3.0);
+        assert!((ci - 1.96).abs() < 1e-9);
+    }
+
+    #[test]
+    fn wilson_interval_contains_the_rate() {
+        let (low, high) = wilson(50, 100);
+        assert!(low < 0.5 && 0.5 < high);
+        assert_eq!(wilson(0, 10).0, 0.0);
+        assert_eq!(wilson(10, 10).1, 1.0);
+    }
+}
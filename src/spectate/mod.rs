@@ -0,0 +1,200 @@
+// Spectators: read-only connections that watch a running game, e.g. to project
+// a final on a screen. The host has to allow them, and they say so in their
+// Hello, see net::hello. A spectator first gets a snapshot of the game and then
+// every shot as it's fired. The spectators only see what the players found out
+// about each other's board, since a player could watch as well. Only if both
+// players offer REVEAL in their hellos, e.g. for a show match, they see both
+// fleets. Hidden boards are never revealed, the host doesn't know them.
+use model::engine::GameEngine;
+use model::types::{Board, PlayerId, ShotOutcome, SubField};
+use net::{self, hello, NetError};
+use net::types::MessageType;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use term_painter::ToStyle;
+use term_painter::Color::*;
+
+/// The state of the game as the spectators see it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub names: [String; 2],
+    pub boards: [Board; 2],
+    pub winner: Option<PlayerId>,
+}
+
+impl Snapshot {
+    fn new(engine: &GameEngine, reveal: bool) -> Snapshot {
+        let board = |id: PlayerId| if reveal {
+            engine.player(id).own_board.clone()
+        } else {
+            engine.player(id.other()).op_board.clone()
+        };
+        Snapshot {
+            names: [
+                engine.player(PlayerId::One).name.clone(),
+                engine.player(PlayerId::Two).name.clone(),
+            ],
+            boards: [board(PlayerId::One), board(PlayerId::Two)],
+            winner: engine.winner(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Spectators {
+    streams: Vec<TcpStream>,
+    // what spectators joining now get to see; None until the ships are placed
+    snapshot: Option<Snapshot>,
+    // both players agreed to show their fleets, see `reveal()`
+    reveal: bool,
+}
+
+/// The spectators of a game, shared by the game and the connections.
+/// They only see what the players found out unless `reveal()` is called.
+#[derive(Clone, Default)]
+pub struct Audience(Arc<Mutex<Spectators>>);
+
+impl Audience {
+    /// Shows both fleets from now on, once both players agreed to it
+    pub fn reveal(&self) {
+        self.lock().reveal = true;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Spectators> {
+        // a game that panicked while holding the lock doesn't concern the spectators
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lets a new spectator watch the game from now on
+    pub fn join(&self, mut stream: TcpStream) -> Result<(), NetError> {
+        let mut spectators = self.lock();
+        match spectators.snapshot {
+            Some(ref snapshot) => net::send(&mut stream, MessageType::Snapshot(snapshot.clone()))?,
+            None => net::send(
+                &mut stream,
+                MessageType::Text("The players are placing their ships.".to_string())
+            )?,
+        }
+        spectators.streams.push(stream);
+        Ok(())
+    }
+
+    /// Shows the whole game, after the placement and at the end
+    pub fn show(&self, engine: &GameEngine) {
+        let mut spectators = self.lock();
+        let snapshot = Snapshot::new(engine, spectators.reveal);
+        broadcast(&mut spectators.streams, &MessageType::Snapshot(snapshot.clone()));
+        spectators.snapshot = Some(snapshot);
+    }
+
+    /// Shows the last shot of the game
+    pub fn fired(&self, engine: &GameEngine) {
+        let mut spectators = self.lock();
+        if let Some(shot) = engine.shots().last() {
+            broadcast(&mut spectators.streams, &MessageType::Fired(shot.clone()));
+        }
+        spectators.snapshot = Some(Snapshot::new(engine, spectators.reveal));
+    }
+
+    /// Ends the connections after the game
+    pub fn close(&self) {
+        let mut spectators = self.lock();
+        broadcast(&mut spectators.streams, &MessageType::Quit);
+        for stream in spectators.streams.drain(..) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Sends the message to every spectator, those who left are dropped
+fn broadcast(streams: &mut Vec<TcpStream>, msg: &MessageType) {
+    let mut present = Vec::new();
+    for mut stream in streams.drain(..) {
+        if net::send(&mut stream, msg.clone()).is_ok() {
+            present.push(stream);
+        }
+    }
+    *streams = present;
+}
+
+/// Watches the game on the server at host:port, in a lobby the match in the given room
+pub fn watch(name: &str, host: &str, port: u16, room: Option<&str>) -> Result<(), NetError> {
+    let addresses = net::resolve(host, port)?;
+    let mut stream = TcpStream::connect(&addresses[..]).map_err(|e| {
+        NetError::Io(format!("could not connect to {}", net::display_address(host, port)), e)
+    })?;
+
+    let mut spectator_hello = hello::Hello::new(name, None, hello::CAPABILITIES);
+    spectator_hello.spectator = true;
+    hello::send(&mut stream, &spectator_hello)?;
+    let host_hello = hello::receive(&mut stream)?;
+    match net::receive(&mut stream)? {
+        MessageType::Welcome(..) => {},
+        MessageType::Refused(reason) => {
+            return Err(NetError::Handshake(format!("the server refused: {}", reason)))
+        },
+        _ => return Err(NetError::Handshake("the server didn't send a welcome".to_string())),
+    }
+    if host_hello.offers(hello::LOBBY) {
+        let room = room.ok_or_else(|| NetError::Handshake(
+            "the server is a lobby, please choose a room with --room".to_string()
+        ))?;
+        net::send(&mut stream, MessageType::Watch(room.to_string()))?;
+    }
+
+    let mut game: Option<Snapshot> = None;
+    loop {
+        match net::receive(&mut stream) {
+            Ok(MessageType::Snapshot(snapshot)) => {
+                let dim = snapshot.boards[0].dim();
+                if snapshot.boards.iter().any(|b| b.dim() != dim || b.size() != dim * dim) {
+                    return Err(NetError::Protocol("the boards don't fit together".to_string()));
+                }
+                show(&snapshot);
+                game = Some(snapshot);
+            },
+            Ok(MessageType::Fired(shot)) => {
+                if let Some(ref mut game) = game {
+                    if shot.field >= game.boards[0].size() {
+                        return Err(NetError::Protocol("shot beyond the board".to_string()));
+                    }
+                    let field = if shot.outcome == ShotOutcome::Miss {
+                        SubField::Miss
+                    } else {
+                        SubField::Hit
+                    };
+                    game.boards[shot.player.other().index()].set(shot.field, field);
+                    show(game);
+                    println!(
+                        "{} fires at {} - {}",
+                        game.names[shot.player.index()],
+                        game.boards[0].coordinate(shot.field),
+                        shot.outcome,
+                    );
+                }
+            },
+            Ok(MessageType::Text(t)) => Cyan.with(|| println!("{}", t)),
+            Ok(MessageType::Refused(reason)) => return Err(NetError::Handshake(reason)),
+            Ok(MessageType::Quit) => break,
+            Ok(_) => return Err(NetError::Protocol("unexpected message".to_string())),
+            // the game may end without a goodbye
+            Err(NetError::Disconnected) if game.as_ref().and_then(|g| g.winner).is_some() => {
+                break
+            },
+            Err(e) => return Err(e),
+        }
+    }
+
+    let _ = stream.shutdown(Shutdown::Both);
+    Yellow.with(|| println!("\nThe game is over."));
+    Ok(())
+}
+
+/// Prints both boards of a snapshot
+fn show(snapshot: &Snapshot) {
+    println!("\n{} (left) vs. {} (right)", snapshot.names[0], snapshot.names[1]);
+    ::view::render::get().print_boards(&snapshot.boards[0], &snapshot.boards[1]);
+    if let Some(id) = snapshot.winner {
+        Yellow.with(|| println!("{} won the game.", snapshot.names[id.index()]));
+    }
+}
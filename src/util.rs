@@ -1,8 +1,13 @@
-use model::types::{Board, SubField};
+use model;
+use model::types::{Board, ShipType, SubField};
 
 /// Reads a string from the terminal/user.
+/// During a network game chat messages are sent on the way, see chat.
 pub fn read_string() -> String {
     use std::io::stdin;
+    if let Some(line) = ::chat::read_line() {
+        return line;
+    }
     let mut buffer = String::new();
     stdin()
         .read_line(&mut buffer)
@@ -25,44 +30,187 @@ pub fn read_usize() -> usize {
     }
 }
 
-/// Reads external board configuration
-/// Does not check for valid ship placement!
-pub fn read_extern_board(f: &str) -> [[SubField; 10]; 10] {
+/// Reads external board configuration for a board of `dim` x `dim` fields
+/// and validates it against the placement rules and the fleet.
+/// Errors name the line and column of the offending field.
+pub fn read_extern_board(f: &str, dim: usize, fleet: &[ShipType]) -> Result<Board, String> {
     use std::fs::File;
     use std::io::BufReader;
     use std::io::BufRead;
-    let file;
 
-    match File::open(f) {
-        Ok(f) => file = f,
-        Err(_) => return Board::init()
-    }
+    let file = match File::open(f) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("Could not open '{}': {}", f, e)),
+    };
 
-    let input = BufReader::new(&file);
-    let mut board = Board::init();
-    let mut id = 0;
-    for line in input.lines() {
-        let l = line.unwrap();
-        if l.starts_with("#") {
+    let mut board = Board::init(dim);
+    // line and column of every field in the file
+    let mut positions = Vec::new();
+    let mut rows = 0;
+
+    for (nr, line) in BufReader::new(&file).lines().enumerate() {
+        let l = match line {
+            Ok(l) => l,
+            Err(e) => return Err(format!("Could not read '{}': {}", f, e)),
+        };
+        if l.starts_with("#") || l.trim().is_empty() {
             continue
         }
-        for c in l.chars() {
+        if rows == dim {
+            return Err(format!("line {}: the board has more than {} rows", nr + 1, dim));
+        }
+
+        let mut fields = 0;
+        for (col, c) in l.chars().enumerate() {
             match c {
-                'X' => {
-                    let row = id / 10;
-                    let col = id % 10;
-                    board[row][col] = SubField::Ship;
-                    id += 1
+                'X' | '-' => {
+                    if c == 'X' && fields < dim {
+                        board.set(rows * dim + fields, SubField::Ship);
+                    }
+                    positions.push((nr + 1, col + 1));
+                    fields += 1;
                 },
-                '-' => id += 1,
-                _ => {}
+                c if c.is_whitespace() => {},
+                _ => return Err(
+                    format!("line {}, column {}: unexpected character '{}'", nr + 1, col + 1, c)
+                ),
             }
         }
+        if fields != dim {
+            return Err(format!("line {}: expected {} fields, found {}", nr + 1, dim, fields));
+        }
+        rows += 1;
+    }
+
+    if rows != dim {
+        return Err(format!("expected {} rows, found {}", dim, rows));
+    }
+
+    model::validate_board(&board, dim, fleet).map_err(|e| match e.field() {
+        Some(idx) => format!(
+            "line {}, column {} ({}): {}",
+            positions[idx].0,
+            positions[idx].1,
+            board.coordinate(idx),
+            e,
+        ),
+        None => e.to_string(),
+    })
+}
+
+/// Reads external ship configuration
+/// Every line consists of the name, the size and the amount of a ship type,
+/// e.g. `Cruiser 4 2`. Lines starting with '#' are ignored.
+pub fn read_extern_ships(f: &str) -> Result<Vec<ShipType>, String> {
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::io::BufRead;
+
+    let file = match File::open(f) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("Could not open '{}': {}", f, e)),
+    };
+
+    let mut ships = Vec::new();
+    for (nr, line) in BufReader::new(&file).lines().enumerate() {
+        let l = match line {
+            Ok(l) => l,
+            Err(e) => return Err(format!("Could not read '{}': {}", f, e)),
+        };
+        if l.starts_with("#") || l.trim().is_empty() {
+            continue
+        }
+
+        // the name may consist of several words, size and amount are the last two
+        let words: Vec<&str> = l.split_whitespace().collect();
+        if words.len() < 3 {
+            return Err(format!("line {}: expected '<name> <size> <amount>'", nr + 1));
+        }
+        let size = words[words.len() - 2].parse::<usize>();
+        let amount = words[words.len() - 1].parse::<usize>();
+        match (size, amount) {
+            (Ok(size), Ok(amount)) => ships.push(ShipType {
+                name: words[..words.len() - 2].join(" "),
+                size: size,
+                amount: amount,
+            }),
+            _ => return Err(format!("line {}: size and amount have to be numbers", nr + 1)),
+        }
     }
 
-    if id != 99 {
-        Board::init();
+    Ok(ships)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    /// Reads the given board configuration of a 6x6 board with a Boat and a Ship
+    fn read(name: &str, lines: &[&str]) -> Result<Board, String> {
+        let fleet = vec![
+            ShipType { name: "Boat".to_string(), size: 2, amount: 1 },
+            ShipType { name: "Ship".to_string(), size: 3, amount: 1 },
+        ];
+        let path = env::temp_dir().join(format!("battleship-{}-{}.txt", name, process::id()));
+        fs::write(&path, lines.join("\n")).unwrap();
+        let board = read_extern_board(path.to_str().unwrap(), 6, &fleet);
+        fs::remove_file(&path).unwrap();
+        board
+    }
+
+    #[test]
+    fn valid_board_file() {
+        let board = read("valid", &[
+            "# a comment",
+            "X X - - - -",
+            "- - - - - -",
+            "",
+            "- - - - - -",
+            "X - - - - -",
+            "X - - - - -",
+            "X - - - - -",
+        ]);
+        assert_eq!(board.map(|board| board.ships().len()), Ok(2));
     }
 
-    board
+    #[test]
+    fn errors_name_line_and_column() {
+        let rows = ["- - - - - -"; 4];
+        let board = |first: &'static str, second: &'static str| {
+            let mut lines = vec![first, second];
+            lines.extend_from_slice(&rows);
+            lines
+        };
+        assert_eq!(
+            read("character", &board("X X - - - -", "- - Y - - -")),
+            Err("line 2, column 5: unexpected character 'Y'".to_string())
+        );
+        assert_eq!(
+            read("fields", &board("X X - - -", "- - - - - -")),
+            Err("line 1: expected 6 fields, found 5".to_string())
+        );
+        assert_eq!(
+            read("shape", &board("X X - - - -", "- X - - - -")),
+            Err("line 1, column 1 (A5): the ship is not a straight line".to_string())
+        );
+        assert_eq!(
+            read("missing", &board("- - - X X -", "- - - - - -")),
+            Err("1 Ship missing".to_string())
+        );
+    }
+
+    #[test]
+    fn wrong_amount_of_rows() {
+        assert_eq!(
+            read("few", &["- - - - - -"; 5]),
+            Err("expected 6 rows, found 5".to_string())
+        );
+        assert_eq!(
+            read("many", &["- - - - - -"; 7]),
+            Err("line 7: the board has more than 6 rows".to_string())
+        );
+    }
 }
@@ -1 +1,620 @@
 // View (GUI) representing current game situation
+//
+// A full-screen interface for the single-player mode: both boards are redrawn
+// in place, the player aims with the arrow keys and sees every ship on the
+// board before placing it. A message log and a status bar replace the output
+// scrolling by. The plain text output of `model` remains available as the
+// fallback, e.g. if the terminal is too small or with `single --plain`.
+// Network games keep the plain text output: their chat reads the terminal line
+// by line on a thread of its own (see chat), which rules out the raw mode of
+// the interface.
+//
+// Everything can be done with the keyboard or the mouse (xterm mouse
+// reporting): click on the opponent's board to fire, click or drag on the own
+// board to place a ship - dragging sets the direction it points to.
+use crossterm::{cursor, event, terminal, ExecutableCommand, QueueableCommand};
+use crossterm::event::{
+    Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetBackgroundColor,
+    SetForegroundColor, ResetColor};
+use model;
+use model::ai::Strategy;
+use model::engine::GameEngine;
+use model::types::{Board, Coord, ErrorType, PlayerId, ShipType, ShotOutcome, SubField};
+use rand::StdRng;
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::thread;
+use std::time::Duration;
+
+pub mod render;
+
+const ME: PlayerId = PlayerId::One;
+const AI: PlayerId = PlayerId::Two;
+
+// columns of a field, of the row labels and between the boards
+const FIELD: u16 = 2;
+const LABEL: u16 = 3;
+const GAP: u16 = 6;
+// lines of the message log
+const LOG: usize = 6;
+// pause before a move of the computer, so that the player can follow it
+const DELAY_MS: u64 = 400;
+
+/// Returns whether the terminal can show the full-screen interface for a
+/// board of the given dimension
+pub fn supported(dim: usize) -> bool {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return false;
+    }
+    match terminal::size() {
+        Ok((width, height)) => width >= screen_width(dim) && height >= screen_height(dim),
+        Err(_) => false,
+    }
+}
+
+fn screen_width(dim: usize) -> u16 {
+    2 * (LABEL + FIELD * dim as u16) + GAP
+}
+
+fn screen_height(dim: usize) -> u16 {
+    // title, headings, column letters, the boards, the log and the status bar
+    dim as u16 + LOG as u16 + 7
+}
+
+/// Plays a round of the single-player mode in the full-screen interface.
+/// The ships are placed first unless the round is resumed.
+pub fn play(mut engine: GameEngine, mut ai: Box<dyn Strategy>, rng: &mut StdRng) {
+    let finished = match Tui::start(engine.player(ME).own_board.dim()) {
+        Ok(mut tui) => tui.run(&mut engine, &mut *ai, rng),
+        Err(e) => Err(e),
+    };
+    match finished {
+        Ok(true) => model::finish_round(&engine, &mut *ai),
+        Ok(false) => println!("The game was left unfinished."),
+        Err(e) => println!("The terminal failed: {}", e),
+    }
+}
+
+/// What the player asked for with a key or the mouse
+enum Input {
+    // move the cursor by rows and columns
+    Move(isize, isize),
+    Confirm,
+    Key(char),
+    Quit,
+    // the terminal was resized, everything needs to be redrawn
+    Redraw,
+    // the left mouse button was pressed or dragged over a field, or the mouse
+    // was moved over it without a button
+    Press(Grid, usize),
+    Drag(Grid, usize),
+    Hover(Grid, usize),
+    // the left mouse button was released over one of the boards, or elsewhere
+    Release(Option<Grid>),
+}
+
+/// The boards on the screen
+#[derive(Copy, Clone, PartialEq)]
+enum Grid {
+    Own,
+    Opponent,
+}
+
+/// What is drawn on top of the boards
+enum Overlay<'a> {
+    None,
+    // the field to fire at on the board of the opponent
+    Aim(usize),
+    // the fields of the ship to place on the own board and whether it fits
+    Ship(&'a [usize], bool),
+}
+
+struct Tui {
+    out: Stdout,
+    dim: usize,
+    cursor: usize,
+    log: Vec<(String, Color)>,
+    // the state of the game and the keys that can be used right now
+    status: String,
+}
+
+impl Tui {
+    /// Switches the terminal to the full-screen mode, until the Tui is dropped
+    fn start(dim: usize) -> io::Result<Tui> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        out.execute(terminal::EnterAlternateScreen)?;
+        out.execute(cursor::Hide)?;
+        out.execute(event::EnableMouseCapture)?;
+        out.execute(terminal::Clear(terminal::ClearType::All))?;
+        Ok(Tui {
+            out: out,
+            dim: dim,
+            cursor: 0,
+            log: Vec::new(),
+            status: String::new(),
+        })
+    }
+
+    /// Places both fleets if needed, lets the players fire alternately and
+    /// shows the result. Returns whether the game was finished.
+    fn run(
+        &mut self,
+        engine: &mut GameEngine,
+        ai: &mut dyn Strategy,
+        rng: &mut StdRng
+    ) -> io::Result<bool> {
+        if !engine.ready() {
+            if engine.player(ME).capacity == 0 && !self.place_ships(engine, rng)? {
+                return Ok(false);
+            }
+            if let Err(e) = model::place_opponent(engine, ai, rng) {
+                let name = engine.player(AI).name.clone();
+                self.message(format!("{} forfeits the game: {}", name, e), Color::Red);
+            }
+        }
+        self.message(
+            "Aim with the arrow keys and fire with Enter, 's' saves the game.".to_string(),
+            Color::Cyan,
+        );
+
+        while engine.winner().is_none() {
+            if engine.current_turn() == ME {
+                let idx = match self.target(engine, ai, rng)? {
+                    Some(idx) => idx,
+                    None => return Ok(false),
+                };
+                if let Ok(outcome) = engine.fire(idx) {
+                    let coord = engine.player(ME).op_board.coordinate(idx);
+                    match outcome {
+                        ShotOutcome::Miss => self.message(
+                            format!("You fire at {}: miss.", coord), Color::Blue
+                        ),
+                        ShotOutcome::Hit => self.message(
+                            format!("You fire at {}: hit!", coord), Color::Green
+                        ),
+                        ShotOutcome::Sunk(name) => self.message(
+                            format!("You fire at {} and sink the {}!", coord, name), Color::Green
+                        ),
+                    }
+                }
+            } else {
+                self.status = format!("{} is thinking...", engine.player(AI).name);
+                let player = engine.player(ME);
+                self.draw(&player.own_board, &player.op_board, Overlay::None)?;
+                thread::sleep(Duration::from_millis(DELAY_MS));
+                self.opponent_move(engine, ai, rng);
+            }
+        }
+
+        let text = if engine.winner() == Some(ME) {
+            "Congratulations, you won the game!"
+        } else {
+            "You lost the game."
+        };
+        self.message(text.to_string(), Color::Yellow);
+        self.status = "Game over, press any key".to_string();
+        let player = engine.player(ME);
+        self.draw(&player.own_board, &player.op_board, Overlay::None)?;
+        self.input()?;
+        Ok(true)
+    }
+
+    /// Lets the computer fire. A strategy that fails forfeits the game.
+    fn opponent_move(&mut self, engine: &mut GameEngine, ai: &mut dyn Strategy, rng: &mut StdRng) {
+        let name = engine.player(AI).name.clone();
+        let idx = match ai.target(&engine.player(AI).op_board, rng) {
+            Ok(idx) => idx,
+            Err(e) => {
+                self.message(format!("{} forfeits the game: {}", name, e), Color::Red);
+                engine.resign(AI);
+                return;
+            },
+        };
+        let outcome = match engine.fire(idx) {
+            Ok(outcome) => outcome,
+            Err(_) => return,
+        };
+        ai.record(&engine.player(AI).op_board, idx, &outcome);
+
+        let coord = engine.player(AI).op_board.coordinate(idx);
+        match outcome {
+            ShotOutcome::Miss => self.message(
+                format!("{} fires at {}: miss.", name, coord), Color::Blue
+            ),
+            ShotOutcome::Hit => self.message(
+                format!("{} fires at {}: hit!", name, coord), Color::Red
+            ),
+            ShotOutcome::Sunk(ship) => self.message(
+                format!("{} fires at {} and sinks your {}!", name, coord, ship), Color::Red
+            ),
+        }
+    }
+
+    /// Lets the player place the fleet one ship after the other.
+    /// Returns false if the player quit.
+    fn place_ships(&mut self, engine: &mut GameEngine, rng: &mut StdRng) -> io::Result<bool> {
+        let fleet = engine.fleet().to_vec();
+        let ships: Vec<&ShipType> = fleet.iter()
+            .flat_map(|ship| (0..ship.amount).map(move |_| ship))
+            .collect();
+        let mut placed = 0;
+        let mut ori = "h";
+        // the field the mouse button was pressed at, while dragging
+        let mut anchor = None;
+        self.status = format!(
+            "{}  {}",
+            "Arrows: move  r: rotate  Enter or click: place  drag: place and point",
+            "c: restart  a: at random  q: quit",
+        );
+        self.announce_ship(&engine.player(ME).own_board, ships[0]);
+
+        while placed < ships.len() {
+            let ship = ships[placed];
+            let fields = outline(self.dim, self.cursor, ship.size, ori);
+            let player = engine.player(ME);
+            let indices = model::ship_indices(&player.own_board, self.cursor, ship.size, ori);
+            let overlay = Overlay::Ship(&fields, indices.is_some());
+            self.draw(&player.own_board, &player.op_board, overlay)?;
+
+            let confirm = match self.input()? {
+                Input::Move(rows, cols) => {
+                    self.move_cursor(rows, cols);
+                    false
+                },
+                Input::Confirm => true,
+                Input::Press(Grid::Own, idx) => {
+                    anchor = Some(idx);
+                    self.cursor = idx;
+                    false
+                },
+                Input::Drag(Grid::Own, idx) => {
+                    if let Some(anchor) = anchor {
+                        let (start, dragged) = pointed(self.dim, anchor, idx, ship.size, ori);
+                        self.cursor = start;
+                        ori = dragged;
+                    }
+                    false
+                },
+                Input::Hover(Grid::Own, idx) => {
+                    self.cursor = idx;
+                    false
+                },
+                // the ship is placed where the button was released, unless
+                // the mouse left the board
+                Input::Release(grid) => anchor.take().is_some() && grid == Some(Grid::Own),
+                Input::Key('r') => {
+                    ori = if ori == "h" { "v" } else { "h" };
+                    false
+                },
+                Input::Key('c') => {
+                    engine.restart_placement(ME);
+                    placed = 0;
+                    self.announce_ship(&engine.player(ME).own_board, ships[0]);
+                    false
+                },
+                Input::Key('a') => {
+                    let placed = model::random_board(self.dim, &fleet, rng).and_then(|board| {
+                        engine.set_board(ME, &board).map_err(|e| e.to_string())
+                    });
+                    match placed {
+                        Ok(()) => return Ok(true),
+                        Err(e) => self.message(e, Color::Red),
+                    }
+                    false
+                },
+                Input::Quit => return Ok(false),
+                _ => false,
+            };
+            if !confirm {
+                continue;
+            }
+            // the engine decides, the cursor may have moved since the preview was drawn
+            match engine.place(ME, ship, self.cursor, ori) {
+                Ok(()) => {
+                    placed += 1;
+                    if placed < ships.len() {
+                        self.announce_ship(&engine.player(ME).own_board, ships[placed]);
+                    }
+                },
+                Err(ErrorType::InvalidField) => self.message(
+                    format!("The {} doesn't fit there.", ship.name), Color::Red
+                ),
+                Err(e) => self.message(
+                    format!("The {} can't be placed: {:?}", ship.name, e), Color::Red
+                ),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Asks for the next ship, or to restart if there's no space left for it
+    fn announce_ship(&mut self, board: &Board, ship: &ShipType) {
+        let fits = (0..board.size()).any(|i| {
+            model::ship_indices(board, i, ship.size, "h").is_some()
+                || model::ship_indices(board, i, ship.size, "v").is_some()
+        });
+        if fits {
+            self.message(
+                format!("Place your {} ({} fields).", ship.name, ship.size), Color::Cyan
+            );
+        } else {
+            self.message(
+                format!("No space left for the {}, press 'c' to restart.", ship.name),
+                Color::Red,
+            );
+        }
+    }
+
+    /// Lets the player aim at the board of the opponent.
+    /// Returns None if the player quit.
+    fn target(
+        &mut self,
+        engine: &GameEngine,
+        ai: &dyn Strategy,
+        rng: &mut StdRng
+    ) -> io::Result<Option<usize>> {
+        loop {
+            self.status = format!(
+                "Your fleet: {} hits left  {}: {} hits left  |  {}",
+                engine.player(ME).capacity,
+                engine.player(AI).name,
+                engine.player(AI).capacity,
+                "Enter or click: fire  s: save  q: quit",
+            );
+            let player = engine.player(ME);
+            self.draw(&player.own_board, &player.op_board, Overlay::Aim(self.cursor))?;
+
+            let fire = match self.input()? {
+                Input::Move(rows, cols) => {
+                    self.move_cursor(rows, cols);
+                    false
+                },
+                Input::Hover(Grid::Opponent, idx) => {
+                    self.cursor = idx;
+                    false
+                },
+                Input::Confirm => true,
+                Input::Press(Grid::Opponent, idx) => {
+                    self.cursor = idx;
+                    true
+                },
+                Input::Key('s') => {
+                    let f = model::save::SAVE_FILE;
+                    match model::save_game(engine, ai, rng, f) {
+                        Ok(()) => self.message(
+                            format!("Game saved, resume it with 'single --resume {}'.", f),
+                            Color::Green,
+                        ),
+                        Err(e) => self.message(e, Color::Red),
+                    }
+                    false
+                },
+                Input::Quit => return Ok(None),
+                _ => false,
+            };
+
+            let board = &engine.player(ME).op_board;
+            if fire && board.get(self.cursor) == SubField::Water {
+                return Ok(Some(self.cursor));
+            } else if fire {
+                let coord = board.coordinate(self.cursor);
+                self.message(format!("You already fired at {}.", coord), Color::Red);
+            }
+        }
+    }
+
+    /// Adds a line to the message log
+    fn message(&mut self, text: String, color: Color) {
+        self.log.push((text, color));
+    }
+
+    /// Moves the cursor, without leaving the board
+    fn move_cursor(&mut self, rows: isize, cols: isize) {
+        let dim = self.dim as isize;
+        let row = (self.cursor as isize / dim + rows).max(0).min(dim - 1);
+        let col = (self.cursor as isize % dim + cols).max(0).min(dim - 1);
+        self.cursor = (row * dim + col) as usize;
+    }
+
+    /// Waits for the next key or mouse event of interest
+    fn input(&mut self) -> io::Result<Input> {
+        loop {
+            let key = match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                Event::Mouse(mouse) => match self.mouse(mouse) {
+                    Some(input) => return Ok(input),
+                    None => continue,
+                },
+                Event::Resize(_, _) => return Ok(Input::Redraw),
+                _ => continue,
+            };
+            return Ok(match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Input::Quit
+                },
+                KeyCode::Up => Input::Move(-1, 0),
+                KeyCode::Down => Input::Move(1, 0),
+                KeyCode::Left => Input::Move(0, -1),
+                KeyCode::Right => Input::Move(0, 1),
+                KeyCode::Enter | KeyCode::Char(' ') => Input::Confirm,
+                KeyCode::Esc | KeyCode::Char('q') => Input::Quit,
+                KeyCode::Char(c) => Input::Key(c.to_ascii_lowercase()),
+                _ => continue,
+            });
+        }
+    }
+
+    /// Translates a mouse event over a field of one of the boards.
+    /// Releasing the button anywhere else still ends a drag.
+    fn mouse(&self, mouse: MouseEvent) -> Option<Input> {
+        let at = self.field_at(mouse.column, mouse.row);
+        match (mouse.kind, at) {
+            (MouseEventKind::Down(MouseButton::Left), Some((grid, idx))) => {
+                Some(Input::Press(grid, idx))
+            },
+            (MouseEventKind::Drag(MouseButton::Left), Some((grid, idx))) => {
+                Some(Input::Drag(grid, idx))
+            },
+            (MouseEventKind::Up(MouseButton::Left), at) => {
+                Some(Input::Release(at.map(|(grid, _)| grid)))
+            },
+            (MouseEventKind::Moved, Some((grid, idx))) => Some(Input::Hover(grid, idx)),
+            _ => None,
+        }
+    }
+
+    /// Returns the board and the field at the given position of the screen
+    fn field_at(&self, column: u16, row: u16) -> Option<(Grid, usize)> {
+        let dim = self.dim as u16;
+        let right = LABEL + FIELD * dim + GAP;
+        if row < 4 || row >= 4 + dim {
+            return None;
+        }
+        for &(left, grid) in &[(0, Grid::Own), (right, Grid::Opponent)] {
+            let start = left + LABEL;
+            if column >= start && column < start + FIELD * dim {
+                let col = (column - start) / FIELD;
+                return Some((grid, ((row - 4) * dim + col) as usize));
+            }
+        }
+        None
+    }
+
+    /// Redraws the whole screen in place: the own board and the view of the
+    /// opponent's board, the message log and the status bar
+    fn draw(&mut self, own: &Board, op: &Board, overlay: Overlay) -> io::Result<()> {
+        let dim = self.dim;
+        let width = terminal::size().map(|size| size.0).unwrap_or(screen_width(dim)) as usize;
+        let right = LABEL + FIELD * dim as u16 + GAP;
+        let colors = render::get().colors();
+        let out = &mut self.out;
+
+        out.queue(cursor::MoveTo(0, 0))?;
+        out.queue(terminal::Clear(terminal::ClearType::All))?;
+        out.queue(SetAttribute(Attribute::Bold))?;
+        let title = "B A T T L E S H I P";
+        out.queue(Print(format!("{:^w$}", title, w = screen_width(dim) as usize)))?;
+        out.queue(SetAttribute(Attribute::Reset))?;
+
+        for &(column, title) in &[(0, "YOUR FLEET"), (right, "OPPONENT")] {
+            out.queue(cursor::MoveTo(column + LABEL, 2))?;
+            out.queue(Print(title))?;
+            out.queue(cursor::MoveTo(column + LABEL, 3))?;
+            for col in 0..dim {
+                out.queue(Print(format!("{:<2}", Coord::column(col))))?;
+            }
+        }
+
+        for row in 0..dim {
+            for &(column, board) in &[(0, own), (right, op)] {
+                out.queue(cursor::MoveTo(column, 4 + row as u16))?;
+                out.queue(Print(format!("{:>2} ", dim - 1 - row)))?;
+                for col in 0..dim {
+                    let idx = row * dim + col;
+                    let (symbol, mut color) = field(board.get(idx), column == 0);
+                    let mut background = None;
+                    match overlay {
+                        Overlay::Aim(aim) if column != 0 && aim == idx => {
+                            background = Some(Color::White);
+                            color = Color::Black;
+                        },
+                        Overlay::Ship(fields, fits) if column == 0 && fields.contains(&idx) => {
+                            background = Some(if fits { Color::DarkGreen } else { Color::DarkRed });
+                            color = Color::White;
+                        },
+                        _ => {},
+                    }
+                    if !colors {
+                        // without colours the overlay is shown in reverse video
+                        if background.is_some() {
+                            out.queue(SetAttribute(Attribute::Reverse))?;
+                        }
+                    } else {
+                        if let Some(background) = background {
+                            out.queue(SetBackgroundColor(background))?;
+                        }
+                        out.queue(SetForegroundColor(color))?;
+                    }
+                    out.queue(Print(symbol))?;
+                    out.queue(SetAttribute(Attribute::Reset))?;
+                    out.queue(ResetColor)?;
+                    out.queue(Print(" "))?;
+                }
+            }
+        }
+
+        let top = 5 + dim as u16;
+        let first = self.log.len().saturating_sub(LOG);
+        for (i, &(ref text, color)) in self.log[first..].iter().enumerate() {
+            out.queue(cursor::MoveTo(1, top + i as u16))?;
+            if colors {
+                out.queue(SetForegroundColor(color))?;
+            }
+            out.queue(Print(text))?;
+            out.queue(ResetColor)?;
+        }
+
+        out.queue(cursor::MoveTo(0, top + LOG as u16 + 1))?;
+        out.queue(SetAttribute(Attribute::Reverse))?;
+        out.queue(Print(format!(" {:<w$}", self.status, w = width - 1)))?;
+        out.queue(SetAttribute(Attribute::Reset))?;
+        out.flush()
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = self.out.execute(event::DisableMouseCapture);
+        let _ = self.out.execute(cursor::Show);
+        let _ = self.out.execute(terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Returns the symbol and the color of a field, on the own board or the
+/// board of the opponent
+fn field(field: SubField, own: bool) -> (char, Color) {
+    let symbol = render::get().symbol(field);
+    match field {
+        SubField::Water => (symbol, Color::DarkBlue),
+        SubField::Ship => (symbol, Color::Grey),
+        SubField::Hit if own => (symbol, Color::Red),
+        SubField::Hit => (symbol, Color::Green),
+        SubField::Miss => (symbol, Color::DarkGrey),
+    }
+}
+
+/// Returns the fields a ship would cover, as far as it stays on the board
+fn outline(dim: usize, start: usize, size: usize, ori: &str) -> Vec<usize> {
+    let (row, col) = (start / dim, start % dim);
+    (0..size)
+        .filter_map(|k| if ori == "v" {
+            row.checked_sub(k).map(|r| r * dim + col)
+        } else if col + k < dim {
+            Some(start + k)
+        } else {
+            None
+        })
+        .collect()
+}
+
+/// Returns the start and the orientation of a ship dragged from `anchor` to
+/// `to`, so that it points from the anchor towards the mouse. Ships only extend
+/// rightwards or upwards from their start, so dragging leftwards or downwards
+/// moves the start. Keeps the orientation while the mouse is on the anchor.
+fn pointed(dim: usize, anchor: usize, to: usize, size: usize, ori: &str) -> (usize, &str) {
+    let (row, col) = ((anchor / dim) as isize, (anchor % dim) as isize);
+    let (rows, cols) = ((to / dim) as isize - row, (to % dim) as isize - col);
+    let length = size as isize - 1;
+    if rows == 0 && cols == 0 {
+        (anchor, ori)
+    } else if cols.abs() >= rows.abs() {
+        let start = if cols > 0 { col } else { (col - length).max(0) };
+        ((row * dim as isize + start) as usize, "h")
+    } else {
+        let start = if rows < 0 { row } else { (row + length).min(dim as isize - 1) };
+        ((start * dim as isize + col) as usize, "v")
+    }
+}
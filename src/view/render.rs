@@ -0,0 +1,196 @@
+// Renderers: how the boards are drawn in the plain text output (and which
+// symbols the full-screen interface uses). Not every terminal or log shows
+// emoji, box-drawing characters or colours, so the renderer is chosen with
+// `--render` or detected from the environment (`NO_COLOR`, `TERM` and the
+// locale).
+use model::types::{Board, Coord, SubField};
+use std::env;
+use std::sync::OnceLock;
+use term_painter::ToStyle;
+use term_painter::Color::*;
+
+static RENDERER: OnceLock<Box<dyn Renderer + Send + Sync>> = OnceLock::new();
+
+/// Draws boards in the terminal
+pub trait Renderer {
+    /// The symbol of a field, exactly one column wide
+    fn symbol(&self, field: SubField) -> char;
+
+    /// The lines and corners of the frame around a board
+    fn frame(&self) -> Frame;
+
+    /// Whether hits are highlighted with colours
+    fn colors(&self) -> bool {
+        true
+    }
+
+    /// Prints the own board and the view of the opponent's board side by side
+    fn print_boards(&self, own: &Board, op: &Board) {
+        let dim = own.dim();
+        let frame = self.frame();
+        // every field takes two columns, the row labels as many as the largest
+        let label = (dim - 1).to_string().len();
+        let inner = 2 * dim + 1;
+        let gap = "      ";
+        let margin = " ".repeat(label + 1);
+
+        println!();
+        println!(
+            "{}{:<w$}{}{}{}",
+            margin, "OWN BOARD", gap, margin, "OPPONENT", w = inner + 2
+        );
+        let line: String = (0..inner).map(|_| frame.horizontal).collect();
+        println!(
+            "{m}{}{l}{}{g}{m}{}{l}{}",
+            frame.top_left, frame.top_right, frame.top_left, frame.top_right,
+            m = margin, l = line, g = gap
+        );
+
+        for row in 0..dim {
+            for &(board, is_own) in &[(own, true), (op, false)] {
+                print!("{:>w$} {}", dim - 1 - row, frame.vertical, w = label);
+                for col in 0..dim {
+                    print!(" ");
+                    let field = board[row][col];
+                    let symbol = self.symbol(field);
+                    match field {
+                        SubField::Hit if self.colors() && is_own => print!("{}", Red.paint(symbol)),
+                        SubField::Hit if self.colors() => print!("{}", Green.paint(symbol)),
+                        _ => print!("{}", symbol),
+                    }
+                }
+                print!(" {}", frame.vertical);
+                if is_own {
+                    print!("{}", gap);
+                }
+            }
+            println!();
+        }
+
+        println!(
+            "{m}{}{l}{}{g}{m}{}{l}{}",
+            frame.bottom_left, frame.bottom_right, frame.bottom_left, frame.bottom_right,
+            m = margin, l = line, g = gap
+        );
+        let letters: String = (0..dim)
+            .map(|col| format!(" {}", Coord::column(col)))
+            .collect();
+        println!("{m} {:<w$}{g}{m} {}", letters, letters, m = margin, g = gap, w = inner + 1);
+        println!();
+    }
+}
+
+/// The characters a frame is drawn with
+pub struct Frame {
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+}
+
+/// Plain ASCII, readable everywhere
+pub struct Ascii;
+
+impl Renderer for Ascii {
+    fn symbol(&self, field: SubField) -> char {
+        match field {
+            SubField::Water => '~',
+            SubField::Ship => '#',
+            SubField::Hit => 'X',
+            SubField::Miss => 'o',
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        Frame {
+            horizontal: '-',
+            vertical: '|',
+            top_left: '+',
+            top_right: '+',
+            bottom_left: '+',
+            bottom_right: '+',
+        }
+    }
+}
+
+/// Unicode symbols and box-drawing characters
+pub struct Unicode;
+
+impl Renderer for Unicode {
+    fn symbol(&self, field: SubField) -> char {
+        match field {
+            SubField::Water => '·',
+            SubField::Ship => '■',
+            SubField::Hit => '✕',
+            SubField::Miss => '○',
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        Frame {
+            horizontal: '─',
+            vertical: '│',
+            top_left: '┌',
+            top_right: '┐',
+            bottom_left: '└',
+            bottom_right: '┘',
+        }
+    }
+}
+
+/// Draws with the symbols of another renderer, but without colours
+pub struct NoColor(pub Box<dyn Renderer + Send + Sync>);
+
+impl Renderer for NoColor {
+    fn symbol(&self, field: SubField) -> char {
+        self.0.symbol(field)
+    }
+
+    fn frame(&self) -> Frame {
+        self.0.frame()
+    }
+
+    fn colors(&self) -> bool {
+        false
+    }
+}
+
+/// Chooses the renderer with the given name: "ascii", "unicode" or "nocolor". Without a name,
+/// or for "nocolor", the symbols depend on whether the terminal handles Unicode.
+/// Colours are left out for "nocolor", if `NO_COLOR` is set or the terminal is dumb.
+pub fn detect(name: Option<&str>) -> Box<dyn Renderer + Send + Sync> {
+    let term = env::var("TERM").unwrap_or_default();
+    let dumb = term.is_empty() || term == "dumb";
+    // the first locale variable that is set decides, like for any other program
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+        .to_lowercase();
+    let unicode = !dumb && (locale.contains("utf-8") || locale.contains("utf8"));
+
+    let symbols: Box<dyn Renderer + Send + Sync> = match name {
+        Some("ascii") => Box::new(Ascii),
+        Some("unicode") => Box::new(Unicode),
+        _ if unicode => Box::new(Unicode),
+        _ => Box::new(Ascii),
+    };
+    let no_color = env::var("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false);
+    if name == Some("nocolor") || no_color || dumb {
+        Box::new(NoColor(symbols))
+    } else {
+        symbols
+    }
+}
+
+/// Sets the renderer for the rest of the program, see `detect()`
+pub fn init(name: Option<&str>) {
+    let _ = RENDERER.set(detect(name));
+}
+
+/// Returns the renderer chosen by `init()`, or the detected one
+pub fn get() -> &'static dyn Renderer {
+    &**RENDERER.get_or_init(|| detect(None))
+}